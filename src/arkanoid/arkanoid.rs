@@ -1,22 +1,20 @@
 use std::f32::consts::PI;
-use std::io;
-use std::io::Write;
-
-use termion::color;
 
+use crate::common::canvas::Canvas;
 use crate::common::consolecolor::Color;
 use crate::common::point::Point;
 
-const BAR_WIDTH: i8 = 5;
+pub const BAR_WIDTH: i8 = 5;
 const BRICK_WIDTH: i8 = 4;
+pub const BRICK_SCORE: u32 = 100;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Brick {
     pub position: Point,
     pub color: Color,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct Ball {
     x: f32,
     y: f32,
@@ -35,7 +33,7 @@ impl Ball {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Arkanoid {
     width: u8,
     height: u8,
@@ -123,7 +121,7 @@ impl Arkanoid {
             ball,
             bar: self.bar.clone(),
             bricks,
-            score: self.score + removed_bricks.len() as u32 * 100,
+            score: self.score + removed_bricks.len() as u32 * BRICK_SCORE,
         })
     }
 
@@ -161,27 +159,53 @@ impl Arkanoid {
         }
     }
 
-    pub fn print<W: Write>(&self, term: &mut W, x: u16, y: u16) -> io::Result<()> {
-        let bar_string = " ".repeat(BAR_WIDTH as usize);
-        let brick_string = " ".repeat(BRICK_WIDTH as usize);
+    /// Moves the middle of the bar to x, as far as the walls let it.
+    pub fn move_to(&self, x: i8) -> Arkanoid {
+        let left = (x - BAR_WIDTH / 2).max(0).min(self.width as i8 - BAR_WIDTH);
+
+        Arkanoid { bar: Point::new(left, self.bar.y), ..self.clone() }
+    }
 
+    pub fn print<C: Canvas>(&self, canvas: &mut C, x: u16, y: u16) {
         for brick in self.bricks.iter() {
-            write!(term, "{}{}{}",
-                   color::Bg(brick.color),
-                   termion::cursor::Goto(brick.position.x as u16 + x + 1, brick.position.y as u16 + y + 1),
-                   brick_string)?;
+            canvas.block(brick.position.x as u16 + x + 1, brick.position.y as u16 + y + 1, BRICK_WIDTH as u16,
+                         brick.color);
         }
 
-        write!(term, "{}{}{}",
-               color::Bg(color::White),
-               termion::cursor::Goto(self.bar.x as u16 + x + 1, self.bar.y as u16 + y + 1),
-               bar_string)?;
-        write!(term, "{}{}*",
-               termion::style::Reset,
-               termion::cursor::Goto(self.ball.x as u16 + x + 1, self.ball.y as u16 + y + 1))
+        canvas.fill(self.bar.x as u16 + x + 1, self.bar.y as u16 + y + 1, BAR_WIDTH as u16, 1, Color::White);
+
+        canvas.put(self.ball.x as u16 + x + 1, self.ball.y as u16 + y + 1, '*', Color::DefaultColor, Color::DefaultColor);
     }
 
     pub fn score(&self) -> u32 {
         self.score
     }
+
+    /// Whether every brick was broken.
+    pub fn cleared(&self) -> bool {
+        self.bricks.is_empty()
+    }
+
+    /// Bricks broken so far.
+    pub fn broken(&self) -> u32 {
+        self.score / BRICK_SCORE
+    }
+
+    /// Where the ball is, as x and y.
+    pub fn ball(&self) -> (f32, f32) {
+        (self.ball.x, self.ball.y)
+    }
+
+    /// The left end of the bar.
+    pub fn bar(&self) -> &Point {
+        &self.bar
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
 }
\ No newline at end of file
@@ -1,90 +1,229 @@
-use std::{io, thread};
-use std::io::{Error, Read, Write};
+use std::io;
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::time::Duration;
 
 use termion::event::Key;
-use termion::input::TermRead;
-
-use crate::arkanoid::arkanoid::Arkanoid;
-use crate::common::ioutils::print_border;
-use crate::common::persistence::HighScores;
-use crate::Main;
 
+use crate::arkanoid::arkanoid::{Arkanoid, BRICK_SCORE};
+use crate::arkanoid::bot;
+use crate::common::canvas::Canvas;
+use crate::common::consolecolor::Color;
+use crate::common::difficulty::Difficulty;
+use crate::common::gameloop::{Flow, Game, GameLoop};
+use crate::common::gamemain::GameMain;
+use crate::common::help::Help;
+use crate::common::keys::{Action, KeyBindings};
+use crate::common::options::GameOptions;
+use crate::common::persistence::Scoring;
+use crate::common::random::GameRng;
+use crate::common::result::{Counter, GameResult, Outcome};
+use crate::common::screen::Screen;
+
+const NAME: &str = "Arkanoid";
+const DIR: &str = "arkanoid";
 const WIDTH: u8 = 40;
 const HEIGHT: u8 = 20;
+const TICK: Duration = Duration::from_millis(5);
+/// how far the ball moves on every tick, at Easy, Normal and Hard
+const DELTAS: [f32; 3] = [0.035, 0.05, 0.07];
+/// The column where the field starts, inside its border.
+const FIELD_X: u16 = 2;
 
 pub struct ArkanoidMain<W: Write> {
+    options: GameOptions,
     _marker: PhantomData<W>,
 }
 
 impl<W: Write> ArkanoidMain<W> {
-    pub fn new() -> ArkanoidMain<W> {
-        ArkanoidMain { _marker: PhantomData }
+    pub fn new(options: GameOptions) -> ArkanoidMain<W> {
+        ArkanoidMain { options, _marker: PhantomData }
+    }
+
+    /// How far the ball moves on every tick, at the difficulty of the options.
+    fn delta(&self) -> f32 {
+        self.options.difficulty.preset(DELTAS) * self.options.difficulty.speed()
     }
 }
 
-impl<W: Write, R: Read> Main<W, R> for ArkanoidMain<W> {
+impl<W: Write, R: Read> GameMain<W, R> for ArkanoidMain<W> {
+    type Game = ArkanoidGame;
+    type Saved = Arkanoid;
+
     fn name(&self) -> &'static str {
-        "Arkanoid"
+        NAME
+    }
+
+    fn dir(&self) -> &'static str {
+        DIR
+    }
+
+    fn options(&self) -> &GameOptions {
+        &self.options
     }
 
-    fn run(&self, stdout: &mut W, stdin: &mut R) -> io::Result<Option<u32>> {
-        let mut arkanoid = Arkanoid::new(WIDTH, HEIGHT);
+    fn scoring(&self) -> Scoring {
+        Scoring::POINTS
+    }
 
-        let mut result: io::Result<Option<u32>> = Result::Ok(None);
+    fn bindings(&self) -> io::Result<KeyBindings> {
+        KeyBindings::load(NAME, default_bindings())
+    }
 
-        'outer: loop {
-            for _i in 0..20 {
-                let mut key_pressed = false;
+    fn explain(&self, bindings: &KeyBindings) -> Help {
+        explain(bindings)
+    }
 
-                if let Some(key_or_error) = stdin.keys().next() {
-                    let key = key_or_error?;
+    fn size(&self) -> (u8, u8) {
+        self.options.size((WIDTH, HEIGHT), (20, 16))
+    }
 
-                    if let Key::Esc = key {
-                        break 'outer;
-                    } else if let Key::Left = key {
-                        arkanoid = arkanoid.left();
-                        key_pressed = true;
-                    } else if let Key::Right = key {
-                        arkanoid = arkanoid.right();
-                        key_pressed = true;
-                    }
-                }
+    /// The ticks are short at every difficulty: the ball moves farther on each.
+    fn tick(&self) -> Duration {
+        TICK
+    }
 
-                if key_pressed {
-                    print(stdout, &arkanoid)?;
-                }
+    fn new_game(&self, width: u8, height: u8, _: &KeyBindings, _: GameRng) -> ArkanoidGame {
+        ArkanoidGame { arkanoid: Arkanoid::new(width, height), delta: self.delta() }
+    }
 
-                if let Some(ark) = arkanoid.next(0.05) {
-                    arkanoid = ark;
-                    print(stdout, &arkanoid)?;
-                } else {
-                    result = Result::Ok(Some(arkanoid.score()));
-                    break 'outer;
-                }
+    fn continue_game(&self, arkanoid: Arkanoid, _: &KeyBindings) -> ArkanoidGame {
+        ArkanoidGame { arkanoid, delta: self.delta() }
+    }
+
+    fn saved(&self, game: ArkanoidGame) -> Arkanoid {
+        game.arkanoid
+    }
+
+    fn game_over(&self, game: &ArkanoidGame, duration: Duration) -> GameResult {
+        let mode = format!("{}x{}", game.arkanoid.width(), game.arkanoid.height());
+        let stats = vec!(("bricks", game.arkanoid.broken()));
+        let outcome = if game.arkanoid.cleared() { Outcome::Cleared } else { Outcome::Lost };
+        let counters = vec!(Counter::Total("bricks broken", game.arkanoid.broken()));
+        let score = Some(game.arkanoid.score());
+        GameResult::new(outcome, score, duration, mode, stats).counting(counters)
+    }
+
+    fn demo(&self, stdout: &mut W, stdin: &mut R, length: Duration) -> io::Result<bool> {
+        let (width, height) = self.options.size((WIDTH, HEIGHT), (20, 16));
+        let bindings = KeyBindings::new(default_bindings());
+        let delta = Difficulty::Normal.preset(DELTAS);
+
+        let game_loop = GameLoop::new(TICK, explain(&bindings), bindings).demoing(length);
+
+        let (flow, _) = game_loop.play(stdout, stdin, None, None, |_|
+            ArkanoidGame { arkanoid: Arkanoid::new(width, height), delta })?;
+
+        Ok(flow != Flow::Quit)
+    }
+}
+
+fn explain(bindings: &KeyBindings) -> Help {
+    Help::new("Break all the bricks with the ball, and don't let it fall past your paddle. \
+               The paddle follows the mouse too.",
+              &format!("{} points for every brick broken.", BRICK_SCORE), bindings)
+}
 
-                thread::sleep(Duration::from_millis(5));
+fn default_bindings() -> Vec<(Action, Vec<Key>)> {
+    vec!(
+        (Action::MoveLeft, vec!(Key::Left)),
+        (Action::MoveRight, vec!(Key::Right))
+    )
+}
+
+pub struct ArkanoidGame {
+    arkanoid: Arkanoid,
+    delta: f32,
+}
+
+impl Game for ArkanoidGame {
+    fn action(&mut self, action: Action) -> io::Result<Flow> {
+        match action {
+            Action::MoveLeft => self.arkanoid = self.arkanoid.left(),
+            Action::MoveRight => self.arkanoid = self.arkanoid.right(),
+            Action::Pointer(_, x, _) => {
+                let x = x.saturating_sub(FIELD_X).min(self.arkanoid.width() as u16);
+                self.arkanoid = self.arkanoid.move_to(x as i8)
             }
+            _ => {}
         }
+        Ok(Flow::Continue)
+    }
 
-        while stdin.keys().next().is_some() {}
+    fn tick(&mut self) -> io::Result<Flow> {
+        match self.arkanoid.next(self.delta) {
+            Some(arkanoid) => {
+                self.arkanoid = arkanoid;
+                Ok(if self.arkanoid.cleared() { Flow::GameOver } else { Flow::Continue })
+            }
+            None => Ok(Flow::GameOver)
+        }
+    }
+
+    fn render(&self, screen: &mut Screen) {
+        print(screen, &self.arkanoid)
+    }
 
-        result
+    fn bot(&self) -> Vec<Action> {
+        bot::actions(&self.arkanoid)
     }
 
-    fn high_scores(&self) -> Result<HighScores, Error> {
-        HighScores::read(".arkanoid")
+    fn size(&self) -> (u16, u16) {
+        (self.arkanoid.width() as u16 + 2, self.arkanoid.height() as u16 + 4)
     }
 }
 
-fn print<W: Write>(term: &mut W, arkanoid: &Arkanoid) -> io::Result<()> {
-    write!(term, "{}{}Score: {}",
-           termion::clear::All,
-           termion::cursor::Goto(1, 1),
-           arkanoid.score()
-    )?;
-    print_border(term, 1, 2, WIDTH as u16 + 2, HEIGHT as u16 + 3)?;
-    arkanoid.print(term, 1, 2)?;
-    term.flush()
+fn print(screen: &mut Screen, arkanoid: &Arkanoid) {
+    screen.text(1, 1, &format!("Score: {}", arkanoid.score()), Color::DefaultColor, Color::DefaultColor);
+    screen.border(1, 2, arkanoid.width() as u16 + 2, arkanoid.height() as u16 + 3);
+    arkanoid.print(screen, 1, 2);
+}
+
+#[cfg(test)]
+use crate::common::headless::{assert_snapshot, LEFT, play, RIGHT, Script};
+#[cfg(test)]
+use crate::common::keys::Button;
+#[cfg(test)]
+use crate::common::theme;
+
+#[test]
+fn arkanoid_should_be_played_until_the_ball_is_lost() {
+    let options = GameOptions { width: Some(20), height: Some(16), seed: None, ..GameOptions::default() };
+    let script = Script::new()
+        .keys(&[LEFT, LEFT, LEFT]).wait(2000)
+        .keys(&[RIGHT, RIGHT, RIGHT, RIGHT, RIGHT, RIGHT]);
+
+    let (score, terminal) = play(&ArkanoidMain::new(options), script, 30, 22);
+
+    assert_eq!(Some(0), score.unwrap().score);
+    assert_snapshot("arkanoid_game_over", &terminal.snapshot());
+}
+
+#[test]
+fn the_paddle_should_follow_the_mouse_within_the_walls() {
+    let mut game = ArkanoidGame { arkanoid: Arkanoid::new(40, 20), delta: 0.05 };
+
+    game.action(Action::Pointer(Button::Left, 22, 10)).unwrap();
+    assert_eq!(18, game.arkanoid.bar().x);
+
+    game.action(Action::Pointer(Button::Left, 1, 10)).unwrap();
+    assert_eq!(0, game.arkanoid.bar().x);
+
+    game.action(Action::Pointer(Button::Left, 200, 10)).unwrap();
+    assert_eq!(35, game.arkanoid.bar().x);
+}
+
+#[test]
+fn without_colors_the_bricks_should_be_told_apart_by_their_glyphs() {
+    theme::set(&theme::MONOCHROME);
+
+    let options = GameOptions { width: Some(20), height: Some(16), seed: None, ..GameOptions::default() };
+    let script = Script::new().wait(2000);
+
+    let (_, terminal) = play(&ArkanoidMain::new(options), script, 30, 22);
+
+    theme::set(&theme::DARK);
+
+    let first = &terminal.frames()[0];
+    assert!(first.contains("@@@") && first.contains("&&&") && first.contains("==="), "{}", first);
 }
@@ -0,0 +1,38 @@
+use crate::arkanoid::arkanoid::{Arkanoid, BAR_WIDTH};
+use crate::common::keys::Action;
+
+/// How far the middle of the bar may be from the ball before the bar follows it.
+const TOLERANCE: f32 = 1.0;
+
+/// Keeps the middle of the bar under the ball.
+pub fn actions(arkanoid: &Arkanoid) -> Vec<Action> {
+    let (ball, _) = arkanoid.ball();
+    let middle = arkanoid.bar().x as f32 + BAR_WIDTH as f32 / 2.0;
+
+    if ball < middle - TOLERANCE {
+        vec!(Action::MoveLeft)
+    } else if ball > middle + TOLERANCE {
+        vec!(Action::MoveRight)
+    } else {
+        Vec::new()
+    }
+}
+
+#[test]
+fn the_bot_should_not_miss_the_ball() {
+    let mut arkanoid = Arkanoid::new(40, 20);
+
+    for _ in 0..5000 {
+        for action in actions(&arkanoid) {
+            arkanoid = if action == Action::MoveLeft { arkanoid.left() } else { arkanoid.right() };
+        }
+
+        arkanoid = arkanoid.next(0.05).expect("the bot should not miss the ball");
+
+        if arkanoid.cleared() {
+            break;
+        }
+    }
+
+    assert!(arkanoid.broken() > 0);
+}
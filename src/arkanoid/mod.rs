@@ -1,2 +1,4 @@
+#[allow(clippy::module_inception)]
 mod arkanoid;
+mod bot;
 pub mod arkanoidmain;
\ No newline at end of file
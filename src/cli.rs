@@ -0,0 +1,230 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::common::options::{GameOptions, MAX_SIZE};
+use crate::common::replay::Speed;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Menu,
+    Play(String),
+    Scores(Option<String>),
+    Replay(String, Speed),
+    List,
+    Help,
+    /// Lets players connect with telnet on the address and port.
+    Serve(IpAddr, u16),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Args {
+    pub command: Command,
+    pub options: GameOptions,
+    /// Where to keep scores, saved games and settings, instead of the user's directories.
+    pub home: Option<PathBuf>,
+}
+
+pub fn usage() -> String {
+    "Usage: consolegames [OPTIONS] [GAME]\n\
+     \n\
+     Opens the games menu, or starts GAME straight away.\n\
+     \n\
+     Options:\n\
+     \x20 -l, --list       list the available games\n\
+     \x20 -s, --scores     show the high scores of GAME, or of every game\n\
+     \x20     --width N    board width, up to 127\n\
+     \x20     --height N   board height, up to 127\n\
+     \x20     --seed N     seed for the random generator, to play the same game again\n\
+     \x20     --difficulty D\n\
+     \x20                  easy, normal (the default), hard, or a custom speed from 50 to 200 (% of normal);\n\
+     \x20                  each difficulty has its own high scores\n\
+     \x20     --replay F   play back the game recorded in F, e.g. ~/.local/share/consolegames/tetris/replay.json\n\
+     \x20                  for the last Tetris\n\
+     \x20     --speed S    replay speed: 1, 2, or step to go forward a tick for every key\n\
+     \x20     --home D     keep scores, saved games and settings in D instead of the user's data and config\n\
+     \x20                  directories; CONSOLEGAMES_HOME does the same\n\
+     \x20     --serve P    let players connect with telnet on port P\n\
+     \x20     --bind A     the address to serve on: 127.0.0.1 (the default) lets in only this machine,\n\
+     \x20                  0.0.0.0 anyone who can reach it\n\
+     \x20 -h, --help       show this help\n".to_string()
+}
+
+pub fn parse<I: Iterator<Item=String>>(args: I) -> Result<Args, String> {
+    let mut options = GameOptions::default();
+    let mut game: Option<String> = None;
+    let mut list = false;
+    let mut scores = false;
+    let mut replay: Option<String> = None;
+    let mut speed = Speed::Normal;
+    let mut home: Option<PathBuf> = None;
+    let mut serve: Option<u16> = None;
+    // only players on this machine, unless told otherwise
+    let mut bind = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    let mut args = args.peekable();
+
+    while let Some(arg) = args.next() {
+        let (name, inline_value) = match arg.find('=') {
+            Some(index) if arg.starts_with("--") => (arg[..index].to_string(), Some(arg[index + 1..].to_string())),
+            _ => (arg.clone(), None)
+        };
+
+        match name.as_str() {
+            "-h" | "--help" => return Ok(Args { command: Command::Help, options, home }),
+            "-l" | "--list" => list = true,
+            "-s" | "--scores" => scores = true,
+            "--width" => options.width = Some(side(&name, inline_value, &mut args)?),
+            "--height" => options.height = Some(side(&name, inline_value, &mut args)?),
+            "--seed" => options.seed = Some(value(&name, inline_value, &mut args)?),
+            "--difficulty" => options.difficulty = value(&name, inline_value, &mut args)?,
+            "--replay" => replay = Some(value(&name, inline_value, &mut args)?),
+            "--speed" => speed = value(&name, inline_value, &mut args)?,
+            "--home" => home = Some(value(&name, inline_value, &mut args)?),
+            "--serve" => serve = Some(value(&name, inline_value, &mut args)?),
+            "--bind" => bind = value(&name, inline_value, &mut args)?,
+            _ if name.starts_with('-') => return Err(format!("Unknown option {}", name)),
+            _ => {
+                if let Some(previous) = game {
+                    return Err(format!("Only one game can be given, found {} and {}", previous, arg));
+                }
+                game = Some(arg);
+            }
+        }
+    }
+
+    let command = if let Some(port) = serve {
+        Command::Serve(bind, port)
+    } else if list {
+        Command::List
+    } else if scores {
+        Command::Scores(game)
+    } else if let Some(replay) = replay {
+        Command::Replay(replay, speed)
+    } else if let Some(game) = game {
+        Command::Play(game)
+    } else {
+        Command::Menu
+    };
+
+    Ok(Args { command, options, home })
+}
+
+/// A game given on the command line matches its name ignoring case, spaces and punctuation,
+/// so "Space Invaders" can be started with "spaceinvaders" and "Wa-tor" with "wator".
+pub fn matches(name: &str, arg: &str) -> bool {
+    normalize(name) == normalize(arg)
+}
+
+pub fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// A board width or height, which can't be larger than `MAX_SIZE`.
+fn side<I: Iterator<Item=String>>(name: &str, inline_value: Option<String>, args: &mut I) -> Result<u8, String> {
+    let side = value(name, inline_value, args)?;
+
+    if side > MAX_SIZE {
+        return Err(format!("Invalid value for {}: {} is more than {}", name, side, MAX_SIZE));
+    }
+
+    Ok(side)
+}
+
+fn value<T: FromStr, I: Iterator<Item=String>>(name: &str, inline_value: Option<String>, args: &mut I) -> Result<T, String> {
+    let value = match inline_value {
+        Some(value) => value,
+        None => args.next().ok_or(format!("Missing value for {}", name))?
+    };
+
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+#[cfg(test)]
+use crate::common::difficulty::Difficulty;
+
+#[cfg(test)]
+fn parse_str(args: &str) -> Result<Args, String> {
+    parse(args.split_whitespace().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn given_no_arguments_then_the_menu_should_be_opened() {
+    assert_eq!(Ok(Args { command: Command::Menu, options: GameOptions::default(), home: None }), parse_str(""));
+}
+
+#[test]
+fn given_a_home_then_it_should_be_kept_with_the_command() {
+    let args = parse_str("--home /media/usb/games snake").unwrap();
+
+    assert_eq!(Command::Play("snake".to_string()), args.command);
+    assert_eq!(Some(PathBuf::from("/media/usb/games")), args.home);
+}
+
+#[test]
+fn given_a_game_then_it_should_be_played_with_its_options() {
+    let args = parse_str("tetris --width 12 --seed=42").unwrap();
+
+    assert_eq!(Command::Play("tetris".to_string()), args.command);
+    assert_eq!(Some(12), args.options.width);
+    assert_eq!(None, args.options.height);
+    assert_eq!(Some(42), args.options.seed);
+    assert_eq!(Difficulty::Normal, args.options.difficulty);
+}
+
+#[test]
+fn given_a_difficulty_then_it_should_be_a_preset_or_a_speed() {
+    assert_eq!(Difficulty::Hard, parse_str("--difficulty hard").unwrap().options.difficulty);
+    assert_eq!(Difficulty::Custom(150), parse_str("--difficulty=150").unwrap().options.difficulty);
+    assert!(parse_str("--difficulty 10").is_err());
+}
+
+#[test]
+fn given_scores_then_the_game_should_be_optional() {
+    assert_eq!(Command::Scores(None), parse_str("--scores").unwrap().command);
+    assert_eq!(Command::Scores(Some("snake".to_string())), parse_str("snake -s").unwrap().command);
+}
+
+#[test]
+fn given_a_replay_then_it_should_be_played_at_the_given_speed() {
+    assert_eq!(Command::Replay("run.json".to_string(), Speed::Normal), parse_str("--replay run.json").unwrap().command);
+    assert_eq!(Command::Replay("run.json".to_string(), Speed::Step), parse_str("--replay=run.json --speed step").unwrap().command);
+    assert!(parse_str("--replay run.json --speed 3").is_err());
+}
+
+#[test]
+fn given_an_invalid_value_then_parse_should_fail() {
+    assert!(parse_str("--width big").is_err());
+    assert!(parse_str("--width 300").is_err());
+    assert!(parse_str("--width 128").is_err());
+    assert!(parse_str("--height").is_err());
+    assert!(parse_str("--unknown").is_err());
+    assert!(parse_str("tetris snake").is_err());
+}
+
+#[test]
+fn names_should_match_ignoring_case_and_punctuation() {
+    assert!(matches("Space Invaders", "spaceinvaders"));
+    assert!(matches("Wa-tor", "WATOR"));
+    assert!(!matches("Tetris", "snake"));
+}
+
+#[test]
+fn given_a_port_then_the_games_should_be_served_on_it() {
+    let args = parse_str("--serve 2323 --difficulty hard").unwrap();
+
+    assert_eq!(Command::Serve(IpAddr::V4(Ipv4Addr::LOCALHOST), 2323), args.command);
+    assert_eq!(Difficulty::Hard, args.options.difficulty);
+    assert!(parse_str("--serve 70000").is_err());
+}
+
+#[test]
+fn given_an_address_then_the_games_should_be_served_on_it() {
+    let args = parse_str("--serve 2323 --bind 0.0.0.0").unwrap();
+
+    assert_eq!(Command::Serve(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 2323), args.command);
+    assert!(parse_str("--serve 2323 --bind anywhere").is_err());
+}
@@ -0,0 +1,220 @@
+use std::io;
+use std::io::Write;
+
+use termion::color;
+
+use crate::common::consolecolor::Color;
+use crate::common::glyphs;
+use crate::common::theme;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cell {
+    pub c: char,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Cell {
+    pub fn blank() -> Cell {
+        Cell { c: ' ', fg: Color::DefaultColor, bg: Color::DefaultColor }
+    }
+}
+
+/// Where the games and boxes are drawn. Backends only put glyphs; the rest is drawn with them. Coordinates start
+/// from 1, as in `termion::cursor::Goto`.
+pub trait Canvas {
+    fn put(&mut self, x: u16, y: u16, c: char, fg: Color, bg: Color);
+
+    fn text(&mut self, x: u16, y: u16, text: &str, fg: Color, bg: Color) {
+        for (i, c) in text.chars().enumerate() {
+            self.put(x + i as u16, y, c, fg, bg);
+        }
+    }
+
+    /// Fills the rectangle with blanks of the given background color.
+    fn fill(&mut self, x: u16, y: u16, width: u16, height: u16, bg: Color) {
+        for iy in y..y + height {
+            for ix in x..x + width {
+                self.put(ix, iy, ' ', Color::DefaultColor, bg);
+            }
+        }
+    }
+
+    fn border(&mut self, x: u16, y: u16, width: u16, height: u16) {
+        if glyphs::current().unicode() {
+            for iy in 0..height {
+                for ix in 0..width {
+                    if ix == 0 || iy == 0 || ix + 1 == width || iy + 1 == height {
+                        self.put(x + ix, y + iy, glyphs::frame(ix, iy, width, height), Color::Border, Color::DefaultColor);
+                    }
+                }
+            }
+            return;
+        }
+
+        self.fill(x, y, width, 1, Color::Border);
+        self.fill(x, y + height - 1, width, 1, Color::Border);
+        self.fill(x, y, 1, height, Color::Border);
+        self.fill(x + width - 1, y, 1, height, Color::Border);
+    }
+
+    /// A block of the color, e.g. a Tetris square or an Arkanoid brick. With Unicode glyphs its last glyph is
+    /// narrower, so that blocks next to each other are told apart.
+    fn block(&mut self, x: u16, y: u16, width: u16, color: Color) {
+        if !glyphs::current().unicode() || theme::current().colorless() {
+            self.fill(x, y, width, 1, color);
+            return;
+        }
+
+        for ix in 0..width {
+            self.put(x + ix, y, if ix + 1 == width { '▉' } else { '█' }, color, Color::DefaultColor);
+        }
+    }
+}
+
+/// Cells in memory, to check what is drawn.
+pub struct Buffer {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+}
+
+impl Buffer {
+    pub fn new(width: u16, height: u16) -> Buffer {
+        Buffer { width, height, cells: vec![Cell::blank(); width as usize * height as usize] }
+    }
+
+    /// Blank outside of the buffer.
+    pub fn cell(&self, x: u16, y: u16) -> Cell {
+        self.index(x, y).map(|i| self.cells[i]).unwrap_or_else(Cell::blank)
+    }
+
+    /// The characters of each row, without the spaces that end it.
+    #[cfg(test)]
+    pub fn lines(&self) -> Vec<String> {
+        self.cells.chunks(self.width as usize)
+            .map(|row| row.iter().map(|cell| cell.c).collect::<String>().trim_end().to_string())
+            .collect()
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        if x < 1 || y < 1 || x > self.width || y > self.height {
+            None
+        } else {
+            Some((y - 1) as usize * self.width as usize + (x - 1) as usize)
+        }
+    }
+}
+
+impl Canvas for Buffer {
+    fn put(&mut self, x: u16, y: u16, c: char, fg: Color, bg: Color) {
+        if let Some(i) = self.index(x, y) {
+            self.cells[i] = Cell { c, fg, bg };
+        }
+    }
+}
+
+/// Writes the glyphs straight away as ANSI escape sequences, for what is drawn once, e.g. the boxes of the menus.
+/// The first error is kept for `finish`, which must be called at the end.
+pub struct Ansi<'a, W: Write> {
+    out: &'a mut W,
+    cursor: Option<(u16, u16)>,
+    fg: Color,
+    bg: Color,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: Write> Ansi<'a, W> {
+    pub fn new(out: &'a mut W) -> Ansi<'a, W> {
+        Ansi { out, cursor: None, fg: Color::DefaultColor, bg: Color::DefaultColor, error: None }
+    }
+
+    /// Puts the colors back as they were.
+    pub fn finish(self) -> io::Result<()> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        write!(self.out, "{}", termion::style::Reset)
+    }
+
+    fn write(&mut self, x: u16, y: u16, c: char, fg: Color, bg: Color) -> io::Result<()> {
+        if self.cursor != Some((x, y)) {
+            write!(self.out, "{}", termion::cursor::Goto(x, y))?;
+        }
+
+        if self.cursor.is_none() || fg != self.fg {
+            write!(self.out, "{}", color::Fg(fg))?;
+        }
+
+        if self.cursor.is_none() || bg != self.bg {
+            write!(self.out, "{}", color::Bg(bg))?;
+        }
+
+        write!(self.out, "{}", theme::current().glyph(c, bg))?;
+
+        self.cursor = Some((x + 1, y));
+        self.fg = fg;
+        self.bg = bg;
+
+        Ok(())
+    }
+}
+
+impl<'a, W: Write> Canvas for Ansi<'a, W> {
+    fn put(&mut self, x: u16, y: u16, c: char, fg: Color, bg: Color) {
+        if self.error.is_none() && x >= 1 && y >= 1 {
+            self.error = self.write(x, y, c, fg, bg).err();
+        }
+    }
+}
+
+#[test]
+fn a_border_should_be_drawn_around_the_rectangle() {
+    let mut buffer = Buffer::new(5, 4);
+
+    buffer.border(1, 2, 4, 3);
+
+    assert_eq!(Color::Border, buffer.cell(1, 3).bg);
+    assert_eq!(Color::DefaultColor, buffer.cell(2, 3).bg);
+    assert_eq!(Cell::blank(), buffer.cell(6, 1));
+
+    glyphs::set(glyphs::Glyphs::Unicode);
+    buffer.border(1, 2, 4, 3);
+    glyphs::set(glyphs::Glyphs::Ascii);
+
+    assert_eq!(vec!("", "┌──┐", "│  │", "└──┘"), buffer.lines());
+}
+
+#[test]
+fn ansi_should_move_the_cursor_and_change_colors_only_when_needed() {
+    let mut out = Vec::new();
+    let mut ansi = Ansi::new(&mut out);
+
+    ansi.text(2, 1, "ab", Color::DefaultColor, Color::DefaultColor);
+    ansi.put(5, 1, 'c', Color::Red, Color::DefaultColor);
+    ansi.finish().unwrap();
+
+    assert_eq!(format!("{}{}{}ab{}{}c{}",
+                       termion::cursor::Goto(2, 1),
+                       color::Fg(Color::DefaultColor),
+                       color::Bg(Color::DefaultColor),
+                       termion::cursor::Goto(5, 1),
+                       color::Fg(Color::Red),
+                       termion::style::Reset),
+               String::from_utf8(out).unwrap());
+}
+
+#[test]
+fn unicode_blocks_should_leave_a_gap_after_them() {
+    glyphs::set(glyphs::Glyphs::Unicode);
+
+    let mut buffer = Buffer::new(6, 1);
+    buffer.block(1, 1, 2, Color::Red);
+    buffer.block(3, 1, 2, Color::Red);
+
+    glyphs::set(glyphs::Glyphs::Ascii);
+
+    assert_eq!(vec!("█▉█▉"), buffer.lines());
+    assert_eq!(Color::Red, buffer.cell(2, 1).fg);
+}
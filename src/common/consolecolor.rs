@@ -1,8 +1,10 @@
 use std::fmt::{Error, Formatter};
 
-use termion::color;
+use crate::common::theme;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// The colors of the games, painted as the current theme says.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[allow(clippy::enum_variant_names)]
 pub enum Color {
     Black,
     Red,
@@ -13,34 +15,15 @@ pub enum Color {
     Cyan,
     White,
     DefaultColor,
+    Border,
 }
 
 impl termion::color::Color for Color {
     fn write_fg(&self, f: &mut Formatter) -> Result<(), Error> {
-        match self {
-            Color::Black => { f.write_str(color::Black.fg_str()) }
-            Color::Red => { f.write_str(color::Red.fg_str()) }
-            Color::Green => { f.write_str(color::Green.fg_str()) }
-            Color::Yellow => { f.write_str(color::Yellow.fg_str()) }
-            Color::Blue => { f.write_str(color::Blue.fg_str()) }
-            Color::Magenta => { f.write_str(color::Magenta.fg_str()) }
-            Color::Cyan => { f.write_str(color::Cyan.fg_str()) }
-            Color::White => { f.write_str(color::White.fg_str()) }
-            Color::DefaultColor => { f.write_str(color::Reset.fg_str()) }
-        }
+        theme::current().write(f, *self, 3)
     }
 
     fn write_bg(&self, f: &mut Formatter) -> Result<(), Error> {
-        match self {
-            Color::Black => { f.write_str(color::Black.bg_str()) }
-            Color::Red => { f.write_str(color::Red.bg_str()) }
-            Color::Green => { f.write_str(color::Green.bg_str()) }
-            Color::Yellow => { f.write_str(color::Yellow.bg_str()) }
-            Color::Blue => { f.write_str(color::Blue.bg_str()) }
-            Color::Magenta => { f.write_str(color::Magenta.bg_str()) }
-            Color::Cyan => { f.write_str(color::Cyan.bg_str()) }
-            Color::White => { f.write_str(color::White.bg_str()) }
-            Color::DefaultColor => { f.write_str(color::Reset.bg_str()) }
-        }
+        theme::current().write(f, *self, 4)
     }
-}
\ No newline at end of file
+}
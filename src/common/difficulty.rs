@@ -0,0 +1,100 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// The slowest and fastest custom speeds, in percent of Normal.
+pub const CUSTOM_SPEEDS: (u32, u32) = (50, 200);
+
+/// Custom plays the Normal preset at another speed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    /// The speed in percent of Normal.
+    Custom(u32),
+}
+
+impl Difficulty {
+    pub const PRESETS: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    /// From the presets for Easy, Normal and Hard.
+    pub fn preset<T: Copy>(&self, presets: [T; 3]) -> T {
+        match self {
+            Difficulty::Easy => presets[0],
+            Difficulty::Normal | Difficulty::Custom(_) => presets[1],
+            Difficulty::Hard => presets[2]
+        }
+    }
+
+    /// 1 unless Custom.
+    pub fn speed(&self) -> f32 {
+        match self {
+            Difficulty::Custom(speed) => *speed as f32 / 100.0,
+            _ => 1.0
+        }
+    }
+
+    /// Of the files of a game, e.g. `scores-hard.json`; none for Normal, as before difficulties.
+    pub fn suffix(&self) -> String {
+        match self {
+            Difficulty::Easy => "-easy".to_string(),
+            Difficulty::Normal => String::new(),
+            Difficulty::Hard => "-hard".to_string(),
+            Difficulty::Custom(speed) => format!("-custom-{}", speed)
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Normal => write!(f, "Normal"),
+            Difficulty::Hard => write!(f, "Hard"),
+            Difficulty::Custom(speed) => write!(f, "Custom {}%", speed)
+        }
+    }
+}
+
+/// easy, normal, hard, or a custom speed in percent of Normal.
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Difficulty, String> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            speed => match speed.trim_end_matches('%').parse() {
+                Ok(speed) if speed >= CUSTOM_SPEEDS.0 && speed <= CUSTOM_SPEEDS.1 => Ok(Difficulty::Custom(speed)),
+                _ => Err(format!("Unknown difficulty {}", s))
+            }
+        }
+    }
+}
+
+#[test]
+fn custom_should_play_the_normal_preset_at_its_speed() {
+    let custom: Difficulty = "150".parse().unwrap();
+
+    assert_eq!(20, custom.preset([10, 20, 30]));
+    assert_eq!(1.5, custom.speed());
+    assert_eq!(30, Difficulty::Hard.preset([10, 20, 30]));
+    assert_eq!(1.0, Difficulty::Hard.speed());
+}
+
+#[test]
+fn each_custom_speed_should_have_its_own_files() {
+    assert_eq!("-custom-50", Difficulty::Custom(50).suffix());
+    assert_eq!("-custom-200", Difficulty::Custom(200).suffix());
+    assert_eq!("", Difficulty::Normal.suffix());
+}
+
+#[test]
+fn difficulties_should_be_parsed_by_name_or_speed() {
+    assert_eq!(Ok(Difficulty::Easy), "Easy".parse());
+    assert_eq!(Ok(Difficulty::Custom(80)), "80%".parse());
+    assert!("300".parse::<Difficulty>().is_err());
+    assert!("extreme".parse::<Difficulty>().is_err());
+}
@@ -0,0 +1,622 @@
+use std::{io, thread};
+use std::cell::RefCell;
+use std::cmp::min;
+use std::io::{Read, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use termion::event::{Event, Key, MouseButton, MouseEvent};
+use termion::input::TermRead;
+
+use crate::common::difficulty::Difficulty;
+use crate::common::help::Help;
+use crate::common::keys::{Action, Button, KeyBindings};
+use crate::common::pause::{pause_menu, PauseChoice};
+use crate::common::random::{GameRng, new_rng, new_seed};
+use crate::common::replay::{Replay, ReplayEvent, Speed};
+use crate::common::screen::Screen;
+use crate::common::terminal;
+
+const FRAME: Duration = Duration::from_millis(16);
+/// After a long stall (e.g. the terminal was suspended) we don't want to run hundreds of ticks to catch up.
+const MAX_TICKS_PER_FRAME: u32 = 10;
+/// How often we look at the terminal size while it is too small.
+const SIZE_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Flow {
+    Continue,
+    GameOver,
+    Quit,
+    Restart,
+    /// The player quits, and the game should be saved to be continued later.
+    SaveAndQuit,
+}
+
+/// A game driven by `GameLoop`: keys and mouse clicks are mapped to actions as soon as they arrive, `tick` advances the
+/// simulation at a fixed rate, and `render` is called, at most once per frame, only when something changed.
+/// `Action::Pause` is handled by the loop itself, and pauses every game in the same way.
+pub trait Game {
+    fn action(&mut self, action: Action) -> io::Result<Flow>;
+
+    fn tick(&mut self) -> io::Result<Flow>;
+
+    /// Draws the whole game on a cleared screen; only what changed since the last frame reaches the terminal.
+    fn render(&self, screen: &mut Screen);
+
+    /// Width and height of what `render` draws; the game is centered in the terminal, which must be at least this big.
+    fn size(&self) -> (u16, u16);
+
+    /// What the computer would play now, in a demo. Games without a bot just run on their own.
+    fn bot(&self) -> Vec<Action> {
+        Vec::new()
+    }
+}
+
+/// Why `run_until_paused` stopped before the game was over.
+enum Interruption {
+    Paused,
+    Resized,
+    Over(Flow),
+}
+
+pub trait Clock {
+    fn now(&self) -> Duration;
+
+    fn sleep(&self, duration: Duration);
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Replaces the system clock in headless tests.
+    pub static TEST_CLOCK: RefCell<Option<Rc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+/// What is recorded of the current game, to replay it.
+struct Recording {
+    seed: u64,
+    ticks: u64,
+    events: Vec<ReplayEvent>,
+    /// A continued game can't be replayed from its start, and neither can a game saved to be continued.
+    complete: bool,
+}
+
+pub struct GameLoop {
+    tick: Duration,
+    frame: Duration,
+    bindings: KeyBindings,
+    help: Help,
+    clock: Rc<dyn Clock>,
+    playback: Option<(Replay, Speed)>,
+    /// How long the game's bot plays, in a demo.
+    demo: Option<Duration>,
+    recording: RefCell<Recording>,
+}
+
+impl GameLoop {
+    pub fn new(tick: Duration, help: Help, bindings: KeyBindings) -> GameLoop {
+        GameLoop::with_clock(tick, help, bindings, new_clock())
+    }
+
+    fn with_clock(tick: Duration, help: Help, bindings: KeyBindings, clock: Rc<dyn Clock>) -> GameLoop {
+        let recording = Recording { seed: 0, ticks: 0, events: Vec::new(), complete: false };
+        GameLoop { tick, frame: FRAME, bindings, help, clock, playback: None, demo: None, recording: RefCell::new(recording) }
+    }
+
+    /// Plays the replay instead of reading the player's actions. Keys only step through it, or stop it.
+    pub fn replaying(mut self, playback: Option<(Replay, Speed)>) -> GameLoop {
+        self.playback = playback;
+        self
+    }
+
+    /// Lets the game's bot play, until the length is over or any key is pressed, which quits.
+    pub fn demoing(mut self, length: Duration) -> GameLoop {
+        self.demo = Some(length);
+        self
+    }
+
+    /// Plays the saved game, if any, then new games until one is not restarted from the pause menu,
+    /// and returns how it ended and the game itself. New games get a generator from the seed, or from
+    /// a random seed, which is recorded.
+    pub fn play<W, R, G, F>(&self, stdout: &mut W, stdin: &mut R, seed: Option<u64>, saved: Option<G>, mut new_game: F)
+                            -> io::Result<(Flow, G)>
+        where W: Write, R: Read, G: Game, F: FnMut(GameRng) -> G {
+        let mut next = saved;
+
+        loop {
+            let complete = next.is_none();
+            let mut game = next.take().unwrap_or_else(|| {
+                let seed = match self.playback {
+                    Some((ref replay, _)) => replay.seed,
+                    None => seed.unwrap_or_else(new_seed)
+                };
+
+                self.recording.replace(Recording { seed, ticks: 0, events: Vec::new(), complete: true });
+                new_game(new_rng(seed))
+            });
+
+            let flow = self.run(stdout, stdin, &mut game)?;
+
+            if !complete || flow == Flow::SaveAndQuit {
+                self.recording.borrow_mut().complete = false;
+            }
+
+            if flow != Flow::Restart {
+                return Ok((flow, game));
+            }
+        }
+    }
+
+    /// The last game played, unless it can't be replayed from its start or it was a replay itself.
+    pub fn replay(&self, game: &str, width: u8, height: u8, difficulty: Difficulty) -> Option<Replay> {
+        let recording = self.recording.borrow();
+
+        if !recording.complete || self.playback.is_some() || self.demo.is_some() {
+            return None;
+        }
+
+        Some(Replay {
+            game: game.to_string(),
+            width,
+            height,
+            seed: recording.seed,
+            difficulty,
+            ticks: recording.ticks,
+            events: recording.events.clone(),
+        })
+    }
+
+    /// Game time of the last game played, which doesn't count pauses nor the time before a save.
+    pub fn duration(&self) -> Duration {
+        self.tick * self.recording.borrow().ticks as u32
+    }
+
+    /// Runs until the game returns something else than `Flow::Continue`, and returns it.
+    pub fn run<W: Write, R: Read, G: Game>(&self, stdout: &mut W, stdin: &mut R, game: &mut G) -> io::Result<Flow> {
+        loop {
+            // the layout is done again after every pause or resize, since the terminal may have changed meanwhile
+            if self.demo.is_some() && !fits(game) {
+                return Ok(Flow::GameOver);
+            }
+
+            if !self.wait_for_size(stdout, stdin, game)? {
+                return Ok(Flow::Quit);
+            }
+
+            let (width, height) = terminal::size();
+            let (game_width, game_height) = game.size();
+            let mut screen = Screen::centered(width, height, game_width, game_height);
+
+            render(stdout, &mut screen, game)?;
+
+            match self.run_until_paused(stdout, stdin, &mut screen, game)? {
+                Interruption::Over(flow) => return Ok(flow),
+                Interruption::Resized => {}
+                Interruption::Paused if self.playback.is_some() => return Ok(Flow::Quit),
+                Interruption::Paused => match pause_menu(stdout, stdin, &self.help)? {
+                    PauseChoice::Resume => {}
+                    PauseChoice::Restart => return Ok(Flow::Restart),
+                    PauseChoice::SaveAndQuit => return Ok(Flow::SaveAndQuit),
+                    PauseChoice::Quit => return Ok(Flow::Quit)
+                }
+            }
+        }
+    }
+
+    /// Asks to enlarge the terminal until the game fits in it. Returns false if the player gives up with Esc.
+    fn wait_for_size<W: Write, R: Read, G: Game>(&self, stdout: &mut W, stdin: &mut R, game: &G) -> io::Result<bool> {
+        let mut shown = None;
+
+        loop {
+            if fits(game) {
+                return Ok(true);
+            }
+
+            let (width, height) = terminal::size();
+            let (game_width, game_height) = game.size();
+
+            if shown != Some((width, height)) {
+                write!(stdout, "{}{}{}Please enlarge the terminal to {}x{}, it is {}x{} now.{}Esc to quit.",
+                       termion::style::Reset,
+                       termion::clear::All,
+                       termion::cursor::Goto(1, 1),
+                       game_width, game_height, width, height,
+                       termion::cursor::Goto(1, 2))?;
+                stdout.flush()?;
+                shown = Some((width, height));
+            }
+
+            if let Some(key) = stdin.keys().next() {
+                if key? == Key::Esc {
+                    return Ok(false);
+                }
+            }
+
+            self.clock.sleep(SIZE_POLL);
+        }
+    }
+
+    /// Stops when the player pauses the game or the terminal is resized. Time spent meanwhile is not accounted,
+    /// since the clock is read again when we are called back.
+    fn run_until_paused<W: Write, R: Read, G: Game>(&self, stdout: &mut W, stdin: &mut R, screen: &mut Screen, game: &mut G)
+                                                   -> io::Result<Interruption> {
+        let mut previous = self.clock.now();
+        let mut last_render = previous;
+        let mut lag = Duration::from_millis(0);
+        let mut dirty = false;
+        let mut steps = 0;
+
+        let speed = self.playback.as_ref().map(|(_, speed)| *speed);
+        let tick = if speed == Some(Speed::Double) { self.tick / 2 } else { self.tick };
+
+        // one iterator for the whole run, so bytes it reads ahead are not lost between events
+        let mut events = stdin.events();
+
+        loop {
+            for event_or_error in events.by_ref() {
+                if self.demo.is_some() {
+                    event_or_error?;
+                    return Ok(Interruption::Over(Flow::Quit));
+                }
+
+                let action = match self.action(event_or_error?, screen) {
+                    Some(Action::Pause) => return Ok(Interruption::Paused),
+                    _ if speed == Some(Speed::Step) => {
+                        steps += 1;
+                        continue;
+                    }
+                    _ if speed.is_some() => continue,
+                    Some(action) => action,
+                    None => continue
+                };
+
+                self.record(action);
+
+                let flow = game.action(action)?;
+                dirty = true;
+
+                if flow != Flow::Continue {
+                    render(stdout, screen, game)?;
+                    return Ok(Interruption::Over(flow));
+                }
+            }
+
+            if terminal::resized() {
+                return Ok(Interruption::Resized);
+            }
+
+            let now = self.clock.now();
+            lag += now - previous;
+            previous = now;
+
+            if speed == Some(Speed::Step) {
+                lag = Duration::from_millis(0);
+            }
+
+            let mut ticks = 0;
+
+            while lag >= tick || steps > 0 {
+                if steps > 0 {
+                    steps -= 1;
+                } else {
+                    lag -= tick;
+                }
+                ticks += 1;
+
+                let flow = self.advance(game)?;
+                dirty = true;
+
+                if flow != Flow::Continue {
+                    render(stdout, screen, game)?;
+                    return Ok(Interruption::Over(flow));
+                }
+
+                if ticks == MAX_TICKS_PER_FRAME {
+                    lag = Duration::from_millis(0);
+                }
+            }
+
+            if dirty && now - last_render >= self.frame {
+                render(stdout, screen, game)?;
+                last_render = now;
+                dirty = false;
+            }
+
+            // we wake up at least once per frame to handle keys
+            self.clock.sleep(min(tick - lag, self.frame));
+        }
+    }
+
+    /// Mouse positions are in the coordinates of the game.
+    fn action(&self, event: Event, screen: &Screen) -> Option<Action> {
+        let (button, x, y) = match event {
+            Event::Key(key) => return self.bindings.action(key),
+            Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) | Event::Mouse(MouseEvent::Hold(x, y)) => (Button::Left, x, y),
+            Event::Mouse(MouseEvent::Press(MouseButton::Right, x, y)) => (Button::Right, x, y),
+            _ => return None
+        };
+
+        screen.area_position(x, y).map(|(x, y)| Action::Pointer(button, x, y))
+    }
+
+    fn record(&self, action: Action) {
+        let mut recording = self.recording.borrow_mut();
+        let tick = recording.ticks;
+        recording.events.push(ReplayEvent { tick, action });
+    }
+
+    /// Runs a tick, after the actions of a replay that came before it, or those of the bot in a demo.
+    fn advance<G: Game>(&self, game: &mut G) -> io::Result<Flow> {
+        let ticks = self.recording.borrow().ticks;
+
+        if let Some(length) = self.demo {
+            if self.duration() >= length {
+                return Ok(Flow::GameOver);
+            }
+
+            for action in game.bot() {
+                let flow = game.action(action)?;
+
+                if flow != Flow::Continue {
+                    return Ok(flow);
+                }
+            }
+        }
+
+        if let Some((ref replay, _)) = self.playback {
+            for event in replay.events.iter().filter(|event| event.tick == ticks) {
+                let flow = game.action(event.action)?;
+
+                if flow != Flow::Continue {
+                    return Ok(flow);
+                }
+            }
+
+            if ticks >= replay.ticks {
+                return Ok(Flow::Quit);
+            }
+        }
+
+        self.recording.borrow_mut().ticks += 1;
+
+        game.tick()
+    }
+}
+
+fn new_clock() -> Rc<dyn Clock> {
+    #[cfg(test)]
+    if let Some(clock) = TEST_CLOCK.with(|clock| clock.borrow().clone()) {
+        return clock;
+    }
+
+    Rc::new(SystemClock::new())
+}
+
+fn fits<G: Game>(game: &G) -> bool {
+    let (width, height) = terminal::size();
+    let (game_width, game_height) = game.size();
+
+    width >= game_width && height >= game_height
+}
+
+fn render<W: Write, G: Game>(stdout: &mut W, screen: &mut Screen, game: &G) -> io::Result<()> {
+    screen.clear();
+    game.render(screen);
+    screen.flush(stdout)
+}
+
+#[cfg(test)]
+use std::cell::Cell;
+#[cfg(test)]
+use crate::common::canvas::Canvas;
+#[cfg(test)]
+use crate::common::consolecolor::Color;
+#[cfg(test)]
+use crate::common::headless::{DOWN, ENTER, ESC, Headless, Script};
+#[cfg(test)]
+use crate::common::terminal::TEST_SIZE;
+
+#[cfg(test)]
+struct Counter {
+    ticks: u32,
+    keys: u32,
+    renders: Cell<u32>,
+    max_ticks: u32,
+}
+
+#[cfg(test)]
+impl Game for Counter {
+    fn action(&mut self, action: Action) -> io::Result<Flow> {
+        self.keys += 1;
+        Ok(if action == Action::Fire { Flow::Quit } else { Flow::Continue })
+    }
+
+    fn tick(&mut self) -> io::Result<Flow> {
+        self.ticks += 1;
+        Ok(if self.ticks == self.max_ticks { Flow::GameOver } else { Flow::Continue })
+    }
+
+    fn render(&self, screen: &mut Screen) {
+        self.renders.set(self.renders.get() + 1);
+        screen.put(1, 1, '#', Color::DefaultColor, Color::DefaultColor);
+    }
+
+    fn size(&self) -> (u16, u16) {
+        (10, 5)
+    }
+
+    fn bot(&self) -> Vec<Action> {
+        vec!(Action::MoveLeft)
+    }
+}
+
+#[cfg(test)]
+fn test_bindings() -> KeyBindings {
+    KeyBindings::new(vec!((Action::MoveLeft, vec!(Key::Char('a'), Key::Char('b'))), (Action::Fire, vec!(Key::Char('q')))))
+}
+
+#[cfg(test)]
+fn test_loop(script: &Script) -> GameLoop {
+    GameLoop::with_clock(Duration::from_millis(100), Help::default(), test_bindings(), Rc::new(script.clock()))
+}
+
+#[test]
+fn ticks_should_follow_the_clock_not_the_renders() {
+    let mut script = Script::new();
+    let game_loop = test_loop(&script);
+    let mut counter = Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 };
+
+    let flow = game_loop.run(&mut Vec::new(), &mut script, &mut counter).unwrap();
+
+    assert_eq!(Flow::GameOver, flow);
+    assert_eq!(10, counter.ticks);
+    assert_eq!(Duration::from_millis(1000), script.clock().now());
+    assert!(counter.renders.get() <= 12);
+}
+
+#[test]
+fn keys_should_be_handled_before_ticks() {
+    let mut script = Script::new().keys(&["a", "b", "q"]);
+    let game_loop = test_loop(&script);
+    let mut counter = Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 };
+
+    let flow = game_loop.run(&mut Vec::new(), &mut script, &mut counter).unwrap();
+
+    assert_eq!(Flow::Quit, flow);
+    assert_eq!(3, counter.keys);
+    assert_eq!(0, counter.ticks);
+}
+
+#[test]
+fn pause_menu_should_ask_before_restarting_the_game_or_quitting() {
+    let mut script = Script::new()
+        .keys(&["a", "p", DOWN, ENTER, ENTER, DOWN, ENTER, DOWN, ENTER, "b", ESC, DOWN, DOWN, DOWN, DOWN, ENTER, ENTER, DOWN, DOWN, DOWN, DOWN, ENTER, DOWN, ENTER]);
+    let game_loop = test_loop(&script);
+    let mut games = 0;
+
+    let (flow, counter) = game_loop.play(&mut Vec::new(), &mut script, None, None, |_| {
+        games += 1;
+        Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 }
+    }).unwrap();
+
+    assert_eq!(Flow::Quit, flow);
+    assert_eq!(2, games);
+    assert_eq!(1, counter.keys);
+}
+
+#[test]
+fn pause_menu_should_not_offer_to_save_to_a_remote_player() {
+    terminal::set_remote(terminal::Remote::default());
+    let mut script = Script::new().keys(&["p", DOWN, DOWN, DOWN, ENTER, DOWN, ENTER]);
+    let game_loop = test_loop(&script);
+    let mut counter = Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 };
+
+    let flow = game_loop.run(&mut Vec::new(), &mut script, &mut counter).unwrap();
+
+    assert_eq!(Flow::Quit, flow);
+}
+
+#[test]
+fn a_recorded_game_should_be_replayed_with_its_actions_at_the_same_ticks() {
+    let mut script = Script::new().wait(250).keys(&["a"]).wait(300).keys(&["b", "a"]);
+    let game_loop = test_loop(&script);
+    game_loop.play(&mut Vec::new(), &mut script, Some(42), None, |_| Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 }).unwrap();
+
+    let replay = game_loop.replay("Counter", 10, 5, Difficulty::Normal).unwrap();
+    let actions: Vec<(u64, Action)> = replay.events.iter().map(|event| (event.tick, event.action)).collect();
+    assert_eq!(vec!((2, Action::MoveLeft), (5, Action::MoveLeft), (5, Action::MoveLeft)), actions);
+    assert_eq!(42, replay.seed);
+    assert_eq!(10, replay.ticks);
+
+    let mut script = Script::new();
+    let game_loop = test_loop(&script).replaying(Some((replay, Speed::Double)));
+    let (flow, counter) = game_loop.play(&mut Vec::new(), &mut script, None, None, |_| Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 }).unwrap();
+
+    assert_eq!(Flow::GameOver, flow);
+    assert_eq!(3, counter.keys);
+    assert_eq!(Duration::from_millis(500), script.clock().now());
+    assert_eq!(None, game_loop.replay("Counter", 10, 5, Difficulty::Normal));
+}
+
+#[test]
+fn a_replay_should_step_a_tick_for_every_key_and_stop_where_the_game_was_quit() {
+    let replay = Replay {
+        game: "Counter".to_string(), width: 10, height: 5, seed: 1, difficulty: Difficulty::Normal, ticks: 3, events: Vec::new()
+    };
+    let mut script = Script::new().keys(&["x", "x"]).wait(5000).keys(&["x", "x"]);
+    let game_loop = test_loop(&script).replaying(Some((replay, Speed::Step)));
+
+    let (flow, counter) = game_loop.play(&mut Vec::new(), &mut script, None, None, |_| Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 }).unwrap();
+
+    assert_eq!(Flow::Quit, flow);
+    assert_eq!(3, counter.ticks);
+}
+
+#[test]
+fn keys_should_not_hurry_a_replay_at_normal_speed() {
+    let replay = Replay {
+        game: "Counter".to_string(), width: 10, height: 5, seed: 1, difficulty: Difficulty::Normal, ticks: 10, events: Vec::new()
+    };
+    let mut script = Script::new().keys(&["x", "x", "x"]);
+    let game_loop = test_loop(&script).replaying(Some((replay, Speed::Normal)));
+
+    let (flow, counter) = game_loop.play(&mut Vec::new(), &mut script, None, None, |_| Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 }).unwrap();
+
+    assert_eq!(Flow::GameOver, flow);
+    assert_eq!(10, counter.ticks);
+    assert_eq!(Duration::from_millis(1000), script.clock().now());
+}
+
+#[test]
+fn a_demo_should_be_played_by_the_bot_until_its_end_or_a_key() {
+    let mut script = Script::new();
+    let game_loop = test_loop(&script).demoing(Duration::from_millis(500));
+    let mut counter = Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 };
+
+    assert_eq!(Flow::GameOver, game_loop.run(&mut Vec::new(), &mut script, &mut counter).unwrap());
+    assert_eq!((5, 5), (counter.keys, counter.ticks));
+    assert_eq!(None, game_loop.replay("Counter", 10, 5, Difficulty::Normal));
+
+    let mut script = Script::new().wait(250).keys(&["x"]);
+    let game_loop = test_loop(&script).demoing(Duration::from_millis(500));
+    let mut counter = Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 };
+
+    assert_eq!(Flow::Quit, game_loop.run(&mut Vec::new(), &mut script, &mut counter).unwrap());
+    assert_eq!(2, counter.ticks);
+}
+
+#[test]
+fn a_small_terminal_should_be_asked_to_be_enlarged_and_the_game_centered_in_it_once_it_is() {
+    let script = Script::new().wait(300).resize(40, 20).wait(300).resize(20, 9);
+    let mut counter = Counter { ticks: 0, keys: 0, renders: Cell::new(0), max_ticks: 10 };
+
+    let (flow, terminal) = Headless::new(40, 20).run(script, |terminal, script| {
+        TEST_SIZE.with(|size| size.set(Some((8, 4))));
+        test_loop(script).run(terminal, script, &mut counter)
+    });
+
+    assert_eq!(Flow::GameOver, flow.unwrap());
+    assert!(terminal.frames()[0].starts_with("Please enlarge the terminal to 10x5"));
+    assert!(terminal.frames().iter().any(|frame| frame.lines().nth(7) == Some("               #")));
+    assert_eq!("\n\n     #\n", &terminal.snapshot()[..9]);
+}
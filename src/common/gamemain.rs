@@ -0,0 +1,128 @@
+use std::io;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use termion::input::TermRead;
+
+use crate::common::gameloop::{Flow, Game, GameLoop};
+use crate::common::help::Help;
+use crate::common::keys::KeyBindings;
+use crate::common::options::GameOptions;
+use crate::common::persistence::{HighScores, Scoring};
+use crate::common::random::GameRng;
+use crate::common::replay::{Replay, Speed};
+use crate::common::result::{GameResult, Outcome};
+use crate::common::savegame::SaveSlot;
+use crate::common::stats::LifetimeStats;
+use crate::Main;
+
+/// What sets a game apart from the others. Every `GameMain` is a `Main`, which plays, continues and replays the game
+/// the same way, and keeps its scores, statistics, save and last replay in its directory.
+pub trait GameMain<W: Write, R: Read> {
+    type Game: Game;
+    /// What is kept in the save slot to continue the game.
+    type Saved: Serialize + DeserializeOwned;
+
+    fn name(&self) -> &'static str;
+
+    fn dir(&self) -> &'static str;
+
+    fn options(&self) -> &GameOptions;
+
+    fn scoring(&self) -> Scoring;
+
+    /// The default keys, with those of the config file over them.
+    fn bindings(&self) -> io::Result<KeyBindings>;
+
+    fn explain(&self, bindings: &KeyBindings) -> Help;
+
+    /// The width and height of a new game.
+    fn size(&self) -> (u8, u8);
+
+    /// How long a tick lasts at the difficulty of the options.
+    fn tick(&self) -> Duration;
+
+    fn new_game(&self, width: u8, height: u8, bindings: &KeyBindings, rng: GameRng) -> Self::Game;
+
+    fn continue_game(&self, saved: Self::Saved, bindings: &KeyBindings) -> Self::Game;
+
+    fn saved(&self, game: Self::Game) -> Self::Saved;
+
+    /// Sums up a game which is over, after the duration of game time.
+    fn game_over(&self, game: &Self::Game, duration: Duration) -> GameResult;
+
+    /// Lets the computer play a Normal game, for the length or until a key is pressed, which returns false.
+    fn demo(&self, stdout: &mut W, stdin: &mut R, length: Duration) -> io::Result<bool>;
+}
+
+impl<W: Write, R: Read, M: GameMain<W, R>> Main<W, R> for M {
+    fn name(&self) -> &'static str {
+        GameMain::name(self)
+    }
+
+    fn run(&self, stdout: &mut W, stdin: &mut R) -> io::Result<GameResult> {
+        play(self, stdout, stdin, None, None)
+    }
+
+    fn resume(&self, stdout: &mut W, stdin: &mut R) -> io::Result<GameResult> {
+        let saved = Main::save_slot(self).take()?;
+        play(self, stdout, stdin, saved, None)
+    }
+
+    fn replay(&self, stdout: &mut W, stdin: &mut R, replay: Replay, speed: Speed) -> io::Result<GameResult> {
+        play(self, stdout, stdin, None, Some((replay, speed)))
+    }
+
+    fn high_scores(&self) -> io::Result<HighScores> {
+        HighScores::read(self.dir(), self.options().difficulty, self.scoring())
+    }
+
+    fn stats(&self) -> io::Result<LifetimeStats> {
+        LifetimeStats::read(self.dir(), self.options().difficulty)
+    }
+
+    fn help(&self) -> io::Result<Help> {
+        self.bindings().map(|bindings| self.explain(&bindings))
+    }
+
+    fn save_slot(&self) -> SaveSlot {
+        SaveSlot::new(self.dir(), self.options().difficulty)
+    }
+
+    fn demo(&self, stdout: &mut W, stdin: &mut R, length: Duration) -> io::Result<bool> {
+        GameMain::demo(self, stdout, stdin, length)
+    }
+}
+
+/// Plays the saved game or new ones, or the replay, records the last game played and saves it if asked to.
+fn play<W, R, M>(main: &M, stdout: &mut W, stdin: &mut R, saved: Option<M::Saved>, playback: Option<(Replay, Speed)>)
+                 -> io::Result<GameResult>
+    where W: Write, R: Read, M: GameMain<W, R> {
+    let (width, height) = main.size();
+
+    let bindings = main.bindings()?;
+
+    let game_loop = GameLoop::new(main.tick(), main.explain(&bindings), bindings.clone()).replaying(playback);
+
+    let saved = saved.map(|saved| main.continue_game(saved, &bindings));
+
+    let (flow, game) = game_loop.play(stdout, stdin, main.options().seed, saved, |rng|
+        main.new_game(width, height, &bindings, rng))?;
+
+    while let Some(Ok(_)) = stdin.keys().next() {}
+
+    let difficulty = main.options().difficulty;
+
+    if let Some(replay) = game_loop.replay(GameMain::name(main), width, height, difficulty) {
+        replay.write_last(main.dir())?;
+    }
+
+    match flow {
+        Flow::GameOver => Ok(main.game_over(&game, game_loop.duration())),
+        Flow::SaveAndQuit => SaveSlot::new(main.dir(), difficulty).save(&main.saved(game))
+            .map(|_| GameResult::unfinished(Outcome::Saved)),
+        _ => Ok(GameResult::unfinished(Outcome::Quit))
+    }
+}
@@ -0,0 +1,135 @@
+use std::cell::Cell;
+use std::{fs, io};
+use std::path::PathBuf;
+
+use crate::common::persistence::{config_file, write_atomically};
+
+/// Where the name of the glyphs chosen in the settings is kept.
+const CONFIG_FILE: &str = "glyphs";
+
+/// What the games are drawn with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Glyphs {
+    /// Blanks on colored backgrounds, for consoles which have nothing more than ASCII.
+    Ascii,
+    /// Borders with box drawing characters, and blocks with block glyphs.
+    Unicode,
+    /// As Unicode, and two cells of Wa-tor in each character, one above the other.
+    HalfBlocks,
+    /// As Unicode, and four cells of Wa-tor in each character, with braille dots.
+    Braille,
+}
+
+pub const ALL: [Glyphs; 4] = [Glyphs::Ascii, Glyphs::Unicode, Glyphs::HalfBlocks, Glyphs::Braille];
+
+impl Glyphs {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Glyphs::Ascii => "ASCII",
+            Glyphs::Unicode => "Unicode",
+            Glyphs::HalfBlocks => "Half blocks",
+            Glyphs::Braille => "Braille"
+        }
+    }
+
+    /// Whether borders and blocks are drawn with glyphs, rather than blanks on a background.
+    pub fn unicode(&self) -> bool {
+        *self != Glyphs::Ascii
+    }
+
+    /// How many cells of Wa-tor are drawn across and down a character.
+    pub fn density(&self) -> (u16, u16) {
+        match self {
+            Glyphs::HalfBlocks => (1, 2),
+            Glyphs::Braille => (2, 2),
+            _ => (1, 1)
+        }
+    }
+}
+
+/// The box drawing character at ix and iy of the border of a box of the given size.
+pub fn frame(ix: u16, iy: u16, width: u16, height: u16) -> char {
+    let (left, right, top, bottom) = (ix == 0, ix + 1 == width, iy == 0, iy + 1 == height);
+
+    match (left, right, top, bottom) {
+        (true, _, true, _) => '┌',
+        (_, true, true, _) => '┐',
+        (true, _, _, true) => '└',
+        (_, true, _, true) => '┘',
+        (true, _, _, _) | (_, true, _, _) => '│',
+        _ => '─'
+    }
+}
+
+/// Which of the 8 dots of a braille character, from U+2800, show the cell at x and y of the 2 by 2 cells it
+/// draws: each cell is a pair of dots, one above the other.
+pub fn braille_dots(x: u16, y: u16) -> u8 {
+    match (x, y) {
+        (0, 0) => 0x01 | 0x02,
+        (0, _) => 0x04 | 0x40,
+        (_, 0) => 0x08 | 0x10,
+        _ => 0x20 | 0x80
+    }
+}
+
+thread_local! {
+    static CURRENT: Cell<Glyphs> = const { Cell::new(Glyphs::Ascii) };
+}
+
+/// ASCII until others are set.
+pub fn current() -> Glyphs {
+    CURRENT.with(|current| current.get())
+}
+
+pub fn set(glyphs: Glyphs) {
+    CURRENT.with(|current| current.set(glyphs));
+}
+
+/// The glyphs of the name, ignoring case.
+pub fn find(name: &str) -> Option<Glyphs> {
+    ALL.iter().find(|glyphs| glyphs.name().eq_ignore_ascii_case(name.trim())).cloned()
+}
+
+/// Sets the glyphs chosen in the settings, if any; an unknown name keeps the current glyphs.
+pub fn load() -> io::Result<()> {
+    let file = file()?;
+
+    if file.exists() {
+        if let Some(glyphs) = find(&fs::read_to_string(file)?) {
+            set(glyphs);
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets the glyphs, and keeps them for the next times.
+pub fn save(glyphs: Glyphs) -> io::Result<()> {
+    set(glyphs);
+
+    let file = file()?;
+    fs::create_dir_all(file.parent().unwrap())?;
+
+    write_atomically(&file, glyphs.name())
+}
+
+fn file() -> io::Result<PathBuf> {
+    config_file(CONFIG_FILE)
+}
+
+#[test]
+fn a_frame_should_have_corners_and_sides() {
+    let frame: Vec<String> = (0..3)
+        .map(|y| (0..4).map(|x| if y == 1 && (1..3).contains(&x) { ' ' } else { frame(x, y, 4, 3) }).collect())
+        .collect();
+
+    assert_eq!(vec!("┌──┐", "│  │", "└──┘"), frame);
+}
+
+#[test]
+fn the_four_cells_of_a_braille_character_should_have_their_own_dots() {
+    let dots: Vec<u8> = [(0, 0), (1, 0), (0, 1), (1, 1)].iter().map(|&(x, y)| braille_dots(x, y)).collect();
+
+    assert_eq!(0xff, dots.iter().fold(0, |all, dots| all | dots));
+    assert_eq!(8, dots.iter().map(|dots| dots.count_ones()).sum::<u32>());
+}
@@ -1,12 +1,11 @@
-use std::io::{Error, ErrorKind, Write};
+use std::io::Error;
 use std::io;
 
-use termion::color;
-
+use crate::common::canvas::Canvas;
 use crate::common::consolecolor::Color;
 use crate::common::point::Point;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Grid {
     pub width: u8,
     pub height: u8,
@@ -37,39 +36,40 @@ impl Grid {
             .map(|(iy, row)|
                 row.iter().enumerate()
                     .map(|(ix, v_color)|
-                        if iy as u8 == y && ix as u8 == x { color.clone() } else { v_color.clone() }
+                        if iy as u8 == y && ix as u8 == x { color } else { *v_color }
                     ).collect::<Vec<_>>()
             ).collect::<Vec<_>>();
         Grid { width: self.width, height: self.height, cells: new_cells }
     }
 
-    pub fn print<W: Write>(&self, term: &mut W, border: bool) -> io::Result<()> {
-        if border { self.print_border_row(term)?; }
+    pub fn print<C: Canvas>(&self, canvas: &mut C, x: u16, y: u16, border: bool) {
+        let offset = if border { 1 } else { 0 };
 
-        for row in &self.cells {
-            if border { write!(term, "{} ", color::Bg(color::White))?; }
+        if border {
+            canvas.border(x, y, self.width as u16 * 2 + 2, self.height as u16 + 2);
+        }
 
-            for color in row {
-                write!(term, "{}  ", color::Bg(*color))?;
-            }
-            if border {
-                write!(term, "{} {}\n\r", color::Bg(color::White), termion::style::Reset)?;
-            } else {
-                write!(term, "{}\n\r", termion::style::Reset)?;
+        for (iy, row) in self.cells.iter().enumerate() {
+            for (ix, color) in row.iter().enumerate() {
+                if *color == Color::DefaultColor {
+                    canvas.fill(x + offset + ix as u16 * 2, y + offset + iy as u16, 2, 1, *color);
+                } else {
+                    canvas.block(x + offset + ix as u16 * 2, y + offset + iy as u16, 2, *color);
+                }
             }
         }
+    }
 
-        if border { self.print_border_row(term)?; }
-
-        term.flush()
+    pub fn occupied(&self, x: u8, y: u8) -> bool {
+        self.cells[y as usize][x as usize] != Color::DefaultColor
     }
 
-    pub fn any_occupied(&self, points: &Vec<Point>) -> io::Result<bool> {
+    pub fn any_occupied(&self, points: &[Point]) -> io::Result<bool> {
         let error = points.iter().any(|point| point.x < 0 || point.x >= self.width as i8
             || point.y < 0 || point.y >= self.height as i8);
 
         if error {
-            return Result::Err(Error::new(ErrorKind::Other, "Out of bounds."));
+            return Result::Err(Error::other("Out of bounds."));
         }
 
         Result::Ok(points.iter().any(|point| {
@@ -77,22 +77,23 @@ impl Grid {
         }))
     }
 
-    pub fn any_vertical_out(&self, points: &Vec<Point>) -> bool {
+    pub fn any_vertical_out(&self, points: &[Point]) -> bool {
         points.iter().any(|point| {
             point.y >= self.height as i8 || point.y < 0
         })
     }
 
-    pub fn any_out(&self, points: &Vec<Point>) -> bool {
-        points.into_iter().any(|point| {
+    pub fn any_out(&self, points: &[Point]) -> bool {
+        points.iter().any(|point| {
             point.x >= self.width as i8 || point.y >= self.height as i8 || point.x < 0 || point.y < 0
         })
     }
 
     pub fn pack(&self) -> (u8, Grid) {
-        let mut new_cells = self.cells.to_vec().into_iter()
+        let mut new_cells = self.cells.iter()
             .filter(|row|
-                row.into_iter().filter(|color| **color == Color::DefaultColor).count() > 0)
+                row.iter().filter(|color| **color == Color::DefaultColor).count() > 0)
+            .cloned()
             .collect::<Vec<_>>();
         let mut packed = 0;
         while new_cells.len() as u8 != self.height {
@@ -101,13 +102,5 @@ impl Grid {
         }
         (packed, Grid { width: self.width, height: self.height, cells: new_cells })
     }
-
-    fn print_border_row<W: Write>(&self, term: &mut W) -> io::Result<()> {
-        write!(term, "{} ", color::Bg(color::White))?;
-        for _ in 0..self.width {
-            write!(term, "  ")?;
-        }
-        write!(term, " {}\n\r", termion::style::Reset)
-    }
 }
 
@@ -0,0 +1,452 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::common::gameloop::{Clock, TEST_CLOCK};
+use crate::common::persistence::TEST_HOME;
+use crate::common::result::GameResult;
+use crate::common::terminal;
+use crate::common::terminal::TEST_SIZE;
+use crate::Main;
+
+/// Nothing should take an hour; this is a game that never ends because the script doesn't end it.
+const TIME_LIMIT: Duration = Duration::from_secs(3600);
+/// Reads of the script while nobody advances the clock, before we decide a key is awaited that will never come.
+const STALLED_READS: u32 = 20;
+/// Stalled reads after which a wait of the script ends, since the clock won't reach its end.
+const WAIT_STALLED_READS: u32 = 5;
+
+/// Time that passes only when the game loop sleeps.
+#[derive(Clone)]
+pub struct FakeClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl FakeClock {
+    pub fn new() -> FakeClock {
+        FakeClock { now: Rc::new(Cell::new(Duration::from_millis(0))) }
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let now = self.now.get() + duration;
+        assert!(now < TIME_LIMIT, "the game did not end in {:?}", TIME_LIMIT);
+        self.now.set(now)
+    }
+}
+
+enum Step {
+    Key(&'static str),
+    Wait(Duration),
+    Resize(u16, u16),
+}
+
+/// Keys typed by a player, one key per read so termion never reads ahead into the next key.
+/// Keys are read as soon as they are asked for, unless a wait on the clock comes before them.
+pub struct Script {
+    steps: VecDeque<Step>,
+    position: usize,
+    clock: FakeClock,
+    waiting_since: Option<Duration>,
+    last_read: Option<Duration>,
+    stalled: u32,
+}
+
+impl Script {
+    pub fn new() -> Script {
+        Script { steps: VecDeque::new(), position: 0, clock: FakeClock::new(), waiting_since: None, last_read: None, stalled: 0 }
+    }
+
+    /// Keys as termion reads them, e.g. "a", "\n" or "\x1b[B".
+    pub fn keys(mut self, keys: &[&'static str]) -> Script {
+        self.steps.extend(keys.iter().map(|key| Step::Key(key)));
+        self
+    }
+
+    pub fn wait(mut self, millis: u64) -> Script {
+        self.steps.push_back(Step::Wait(Duration::from_millis(millis)));
+        self
+    }
+
+    /// Resizes the terminal when the keys before have been read.
+    pub fn resize(mut self, width: u16, height: u16) -> Script {
+        self.steps.push_back(Step::Resize(width, height));
+        self
+    }
+
+    pub fn clock(&self) -> FakeClock {
+        self.clock.clone()
+    }
+
+    fn nothing_to_read(&mut self) -> io::Result<usize> {
+        // the game loop sleeps between reads, a menu waiting for a key doesn't
+        let now = self.clock.now();
+
+        if self.last_read == Some(now) {
+            self.stalled += 1;
+            assert!(self.stalled < STALLED_READS, "waiting for a key that the script never gives");
+        } else {
+            self.stalled = 0;
+        }
+
+        self.last_read = Some(now);
+        Ok(0)
+    }
+}
+
+pub const UP: &str = "\x1b[A";
+pub const DOWN: &str = "\x1b[B";
+pub const RIGHT: &str = "\x1b[C";
+pub const LEFT: &str = "\x1b[D";
+pub const ENTER: &str = "\n";
+pub const ESC: &str = "\x1b";
+
+impl Read for Script {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.steps.front() {
+                None => return self.nothing_to_read(),
+                Some(Step::Wait(duration)) => {
+                    let now = self.clock.now();
+                    let since = *self.waiting_since.get_or_insert(now);
+
+                    // menus don't move the clock: when one waits for a key, e.g. after the game is over, so do we
+                    if now - since < *duration && self.stalled < WAIT_STALLED_READS {
+                        return self.nothing_to_read();
+                    }
+
+                    self.steps.pop_front();
+                    self.waiting_since = None;
+                }
+                Some(Step::Resize(width, height)) => {
+                    terminal::resize_test(*width, *height);
+                    self.steps.pop_front();
+                }
+                Some(Step::Key(key)) => {
+                    let key = key.as_bytes();
+                    let len = buf.len().min(key.len() - self.position);
+                    buf[..len].copy_from_slice(&key[self.position..self.position + len]);
+                    self.position += len;
+
+                    if self.position == key.len() {
+                        self.steps.pop_front();
+                        self.position = 0;
+                    }
+
+                    return Ok(len);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VirtualCell {
+    pub c: char,
+    pub fg: Option<u8>,
+    pub bg: Option<u8>,
+}
+
+impl VirtualCell {
+    fn blank() -> VirtualCell {
+        VirtualCell { c: ' ', fg: None, bg: None }
+    }
+}
+
+/// A terminal in memory, which understands the escape sequences termion writes.
+/// Every flush keeps a copy of the screen, so the frames the player saw can be checked afterwards.
+pub struct VirtualTerminal {
+    width: u16,
+    height: u16,
+    cells: Vec<VirtualCell>,
+    x: u16,
+    y: u16,
+    fg: Option<u8>,
+    bg: Option<u8>,
+    pending: Vec<u8>,
+    frames: Vec<String>,
+}
+
+impl VirtualTerminal {
+    pub fn new(width: u16, height: u16) -> VirtualTerminal {
+        VirtualTerminal {
+            width,
+            height,
+            cells: vec![VirtualCell::blank(); width as usize * height as usize],
+            x: 0,
+            y: 0,
+            fg: None,
+            bg: None,
+            pending: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Coordinates start from 1, as in `termion::cursor::Goto`.
+    pub fn cell(&self, x: u16, y: u16) -> VirtualCell {
+        self.cells[(y - 1) as usize * self.width as usize + (x - 1) as usize]
+    }
+
+    /// The screen as text, with trailing spaces removed. Blanks painted with a background color are shown as '█'.
+    pub fn snapshot(&self) -> String {
+        let mut snapshot = String::new();
+
+        for row in self.cells.chunks(self.width as usize) {
+            let line: String = row.iter()
+                .map(|cell| if cell.c == ' ' && cell.bg.is_some() { '█' } else { cell.c })
+                .collect();
+            snapshot.push_str(line.trim_end());
+            snapshot.push('\n');
+        }
+
+        snapshot
+    }
+
+    /// The snapshots of the screen at every flush.
+    pub fn frames(&self) -> &[String] {
+        &self.frames
+    }
+
+    /// Handles what is pending, stopping at an incomplete sequence or character.
+    fn interpret(&mut self) {
+        let mut i = 0;
+
+        while i < self.pending.len() {
+            let consumed = if self.pending[i] == 0x1b {
+                self.escape(i)
+            } else {
+                self.character(i)
+            };
+
+            match consumed {
+                Some(len) => i += len,
+                None => break
+            }
+        }
+
+        self.pending.drain(..i);
+    }
+
+    fn escape(&mut self, start: usize) -> Option<usize> {
+        let bytes = &self.pending[start..];
+
+        if bytes.len() < 2 {
+            return None;
+        }
+
+        if bytes[1] != b'[' {
+            return Some(2);
+        }
+
+        let end = bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b))? + 2;
+        let params = String::from_utf8_lossy(&bytes[2..end]).into_owned();
+        let command = bytes[end];
+
+        self.control(&params, command);
+
+        Some(end + 1)
+    }
+
+    fn control(&mut self, params: &str, command: u8) {
+        if params.starts_with('?') {
+            // showing and hiding the cursor
+            return;
+        }
+
+        let numbers: Vec<u16> = params.split(';').map(|n| n.parse().unwrap_or(0)).collect();
+
+        match command {
+            b'H' => {
+                self.y = numbers[0].max(1) - 1;
+                self.x = numbers.get(1).cloned().unwrap_or(1).max(1) - 1;
+            }
+            b'J' if numbers[0] == 2 => self.cells = vec![VirtualCell::blank(); self.cells.len()],
+            b'm' => self.style(&numbers),
+            _ => {}
+        }
+    }
+
+    fn style(&mut self, numbers: &[u16]) {
+        let mut i = 0;
+
+        while i < numbers.len() {
+            match numbers[i] {
+                0 => {
+                    self.fg = None;
+                    self.bg = None;
+                }
+                // true colors are kept as their red component
+                38 | 48 if numbers.len() > i + 2 => {
+                    let color = Some(numbers[i + 2] as u8);
+
+                    if numbers[i] == 38 { self.fg = color } else { self.bg = color }
+                    i += if numbers[i + 1] == 2 { 4 } else { 2 };
+                }
+                n @ 30..=37 => self.fg = Some(n as u8 - 30),
+                n @ 40..=47 => self.bg = Some(n as u8 - 40),
+                n @ 90..=97 => self.fg = Some(n as u8 - 90 + 8),
+                n @ 100..=107 => self.bg = Some(n as u8 - 100 + 8),
+                39 => self.fg = None,
+                49 => self.bg = None,
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    fn character(&mut self, start: usize) -> Option<usize> {
+        let bytes = &self.pending[start..];
+        let len = match bytes[0] {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            _ => 4
+        };
+
+        if bytes.len() < len {
+            return None;
+        }
+
+        let c = std::str::from_utf8(&bytes[..len]).ok().and_then(|s| s.chars().next()).unwrap_or('?');
+
+        match c {
+            '\r' => self.x = 0,
+            '\n' => self.y += 1,
+            _ => {
+                if self.x < self.width && self.y < self.height {
+                    let index = self.y as usize * self.width as usize + self.x as usize;
+                    self.cells[index] = VirtualCell { c, fg: self.fg, bg: self.bg };
+                }
+                self.x += 1;
+            }
+        }
+
+        Some(len)
+    }
+}
+
+impl Write for VirtualTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        self.interpret();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let snapshot = self.snapshot();
+        self.frames.push(snapshot);
+        Ok(())
+    }
+}
+
+/// Where a headless game runs: a terminal of the given size, the clock of the script, and a temporary home
+/// directory, which stays for the life of this value so what a game saves can be used by the next one.
+pub struct Headless {
+    width: u16,
+    height: u16,
+    home: PathBuf,
+}
+
+impl Headless {
+    pub fn new(width: u16, height: u16) -> Headless {
+        static HOMES: AtomicUsize = AtomicUsize::new(0);
+
+        let home = std::env::temp_dir()
+            .join(format!("consolegames-test-{}-{}", process::id(), HOMES.fetch_add(1, Ordering::Relaxed)));
+
+        Headless { width, height, home }
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    pub fn run<T, F>(&self, mut script: Script, run: F) -> (io::Result<T>, VirtualTerminal)
+        where F: FnOnce(&mut VirtualTerminal, &mut Script) -> io::Result<T> {
+        let mut terminal = VirtualTerminal::new(self.width, self.height);
+
+        TEST_CLOCK.with(|clock| *clock.borrow_mut() = Some(Rc::new(script.clock())));
+        TEST_SIZE.with(|size| size.set(Some((self.width, self.height))));
+        TEST_HOME.with(|home| *home.borrow_mut() = Some(self.home.clone()));
+
+        let result = run(&mut terminal, &mut script);
+
+        TEST_CLOCK.with(|clock| *clock.borrow_mut() = None);
+        TEST_SIZE.with(|size| size.set(None));
+        TEST_HOME.with(|home| *home.borrow_mut() = None);
+
+        (result, terminal)
+    }
+}
+
+impl Drop for Headless {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.home);
+    }
+}
+
+/// Runs a new game in a terminal of the given size, with the keys and the clock of the script.
+pub fn play<M: Main<VirtualTerminal, Script>>(main: &M, script: Script, width: u16, height: u16)
+                                              -> (io::Result<GameResult>, VirtualTerminal) {
+    Headless::new(width, height).run(script, |terminal, script| main.run(terminal, script))
+}
+
+/// Compares with `snapshots/<name>.txt`, which is written instead when UPDATE_SNAPSHOTS is set.
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "snapshots", &format!("{}.txt", name)].iter().collect();
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e|
+        panic!("no snapshot {} ({}), run the tests with UPDATE_SNAPSHOTS=1 to write it:\n{}", path.display(), e, actual));
+
+    assert!(expected == actual, "{} differs from the snapshot:\n{}\nexpected:\n{}", name, actual, expected);
+}
+
+#[test]
+fn virtual_terminal_should_interpret_what_the_screen_writes() {
+    let mut terminal = VirtualTerminal::new(6, 2);
+
+    write!(terminal, "{}{}ab{}{}c{}",
+           termion::clear::All,
+           termion::cursor::Goto(2, 1),
+           termion::cursor::Goto(5, 2),
+           termion::color::Bg(termion::color::Red),
+           termion::style::Reset).unwrap();
+    terminal.write_all(&"é ".as_bytes()[..1]).unwrap();
+    terminal.write_all(&"é ".as_bytes()[1..]).unwrap();
+    terminal.flush().unwrap();
+
+    assert_eq!(" ab\n    cé\n", terminal.frames()[0]);
+    assert_eq!(Some(1), terminal.cell(5, 2).bg);
+    assert_eq!(None, terminal.cell(6, 2).bg);
+}
+
+#[test]
+fn script_should_wait_for_the_clock() {
+    let mut script = Script::new().keys(&["a"]).wait(100).keys(&["b"]);
+    let clock = script.clock();
+    let mut buf = [0; 2];
+
+    assert_eq!(1, script.read(&mut buf).unwrap());
+    assert_eq!(0, script.read(&mut buf).unwrap());
+    clock.sleep(Duration::from_millis(100));
+    assert_eq!(1, script.read(&mut buf).unwrap());
+    assert_eq!(b'b', buf[0]);
+}
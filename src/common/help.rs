@@ -0,0 +1,88 @@
+use crate::common::keys::KeyBindings;
+
+const LINE_WIDTH: usize = 44;
+const KEYS_WIDTH: usize = 12;
+
+/// What to do in a game, how it is scored, and its keys.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Help {
+    pub objective: String,
+    pub scoring: String,
+    pub controls: Vec<(String, &'static str)>,
+}
+
+impl Help {
+    pub fn new(objective: &str, scoring: &str, bindings: &KeyBindings) -> Help {
+        Help { objective: objective.to_string(), scoring: scoring.to_string(), controls: bindings.controls() }
+    }
+
+    /// For the pause menu, a line for each control.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = [&self.objective, &self.scoring].iter()
+            .filter(|text| !text.is_empty())
+            .flat_map(|text| wrap(text, LINE_WIDTH))
+            .collect();
+
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        lines.extend(self.controls.iter().map(|(keys, action)| format!("{:<width$}{}", keys, action, width = KEYS_WIDTH)));
+
+        lines
+    }
+
+    /// For the screen before a game, in lines of at most `width`.
+    pub fn summary(&self, width: usize) -> Vec<String> {
+        let controls: Vec<String> = self.controls.iter().map(|(keys, action)| format!("{} {}", keys, action)).collect();
+
+        [self.objective.clone(), self.scoring.clone(), format!("Controls: {}.", controls.join(", "))].iter()
+            .filter(|text| !text.is_empty())
+            .flat_map(|text| wrap(text, width))
+            .collect()
+    }
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+use termion::event::Key;
+
+#[cfg(test)]
+use crate::common::keys::Action;
+
+#[test]
+fn text_should_be_wrapped_between_words() {
+    assert_eq!(vec!("Clear lines", "by filling", "them"), wrap("Clear lines by filling them", 11));
+    assert!(wrap("", 10).is_empty());
+}
+
+#[test]
+fn the_controls_should_be_those_of_the_bindings() {
+    let bindings = KeyBindings::new(vec!((Action::Fire, vec!(Key::Char(' ')))));
+
+    let help = Help::new("Shoot them all.", "", &bindings);
+
+    assert_eq!(vec!("Shoot them all.", "", "Space       fire", "p/Esc       pause"), help.lines());
+    assert_eq!(vec!("Shoot them all.", "Controls: Space fire, p/Esc pause."), help.summary(80));
+}
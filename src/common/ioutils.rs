@@ -2,32 +2,26 @@ use std::{io, thread};
 use std::io::{Read, Write};
 use std::time::Duration;
 
-use termion::color;
 use termion::event::Key;
 use termion::input::TermRead;
 
-pub fn print_border<W: Write>(stdout: &mut W, x: u16, y: u16, width: u16, height: u16) -> io::Result<()> {
-    write!(stdout, "{}", color::Bg(color::White))?;
-
-    print_border_row(stdout, x, y, width)?;
-    print_border_row(stdout, x, y + height as u16 - 1, width)?;
+use crate::common::canvas::{Ansi, Canvas};
 
-    for iy in 1..(height - 1) {
-        write!(stdout, "{} ", termion::cursor::Goto(x, iy as u16 + y))?;
-        write!(stdout, "{} ", termion::cursor::Goto(x + width as u16 - 1, iy as u16 + y))?;
-    }
+pub fn print_border<W: Write>(stdout: &mut W, x: u16, y: u16, width: u16, height: u16) -> io::Result<()> {
+    let mut ansi = Ansi::new(stdout);
+    ansi.border(x, y, width, height);
+    ansi.finish()
+}
 
+pub fn clear_rec<W: Write>(stdout: &mut W, x: u16, y: u16, width: u16, height: u16) -> io::Result<()> {
+    let row = " ".repeat(width as usize);
     write!(stdout, "{}", termion::style::Reset)?;
-
+    for iy in y..(y + height) {
+        write!(stdout, "{}{}", termion::cursor::Goto(x, iy), row)?;
+    }
     Result::Ok(())
 }
 
-fn print_border_row<W: Write>(term: &mut W, x: u16, y: u16, width: u16) -> io::Result<()> {
-    write!(term, "{}{}",
-           termion::cursor::Goto(x, y),
-           " ".repeat(width as usize))
-}
-
 pub fn wait_for_key_async<R: Read>(stdin: &mut R, key: Key) -> io::Result<()> {
     loop {
         if let Some(key_or_error) = stdin.keys().next() {
@@ -39,4 +33,13 @@ pub fn wait_for_key_async<R: Read>(stdin: &mut R, key: Key) -> io::Result<()> {
         }
         thread::sleep(Duration::from_millis(50));
     }
-}
\ No newline at end of file
+}
+
+pub fn wait_for_any_key_async<R: Read>(stdin: &mut R) -> io::Result<Key> {
+    loop {
+        if let Some(key_or_error) = stdin.keys().next() {
+            return key_or_error;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
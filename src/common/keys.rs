@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+use termion::event::Key;
+
+use crate::common::persistence::config_file;
+
+const CONFIG_FILE: &str = "keys.json";
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    RotateCW,
+    RotateCCW,
+    HardDrop,
+    Fire,
+    Pause,
+    /// The mouse pressed or dragged in the game, from 1 as in `Screen`.
+    Pointer(Button, u16, u16),
+}
+
+/// Dragging counts as the left button.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum Button {
+    Left,
+    Right,
+}
+
+impl Action {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move left",
+            Action::MoveRight => "move right",
+            Action::MoveUp => "move up",
+            Action::MoveDown => "move down",
+            Action::RotateCW => "rotate clockwise",
+            Action::RotateCCW => "rotate counterclockwise",
+            Action::HardDrop => "drop",
+            Action::Fire => "fire",
+            Action::Pause => "pause",
+            Action::Pointer(..) => "click",
+        }
+    }
+}
+
+/// Maps keys to the actions of a game, e.g. `{ "tetris": { "MoveLeft": ["a", "Left"] } }` in the config file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+type Config = HashMap<String, HashMap<Action, Vec<String>>>;
+
+impl KeyBindings {
+    /// Adds Pause unless the defaults have it.
+    pub fn new(defaults: Vec<(Action, Vec<Key>)>) -> KeyBindings {
+        let mut bindings = defaults;
+
+        if !bindings.iter().any(|(action, _keys)| *action == Action::Pause) {
+            bindings.push((Action::Pause, vec!(Key::Char('p'), Key::Esc)));
+        }
+
+        KeyBindings { bindings }
+    }
+
+    /// The defaults with the overrides of the config file, if there is one.
+    pub fn load(game: &str, defaults: Vec<(Action, Vec<Key>)>) -> io::Result<KeyBindings> {
+        let bindings = KeyBindings::new(defaults);
+
+        let file = KeyBindings::file()?;
+
+        if !file.exists() {
+            return Ok(bindings);
+        }
+
+        let contents = fs::read_to_string(&file)?;
+
+        let config: Config = serde_json::from_str(&contents)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", file.display(), e)))?;
+
+        bindings.with_config(game, &config)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", file.display(), e)))
+    }
+
+    fn with_config(self, game: &str, config: &Config) -> Result<KeyBindings, String> {
+        let overrides = config.iter()
+            .find(|(name, _overrides)| crate::cli::matches(game, name))
+            .map(|(_name, overrides)| overrides);
+
+        let mut bindings = self.bindings;
+
+        if let Some(overrides) = overrides {
+            for (action, names) in overrides {
+                let keys = names.iter()
+                    .map(|name| parse_key(name).ok_or(format!("unknown key \"{}\"", name)))
+                    .collect::<Result<Vec<Key>, String>>()?;
+
+                // a key can do only one thing
+                for (_action, other_keys) in bindings.iter_mut() {
+                    other_keys.retain(|key| !keys.contains(key));
+                }
+
+                match bindings.iter_mut().find(|(a, _keys)| a == action) {
+                    Some((_action, action_keys)) => *action_keys = keys,
+                    None => bindings.push((*action, keys))
+                }
+            }
+        }
+
+        // without Pause there would be no way to quit the game
+        if !bindings.iter().any(|(action, keys)| *action == Action::Pause && !keys.is_empty()) {
+            return Err("no key is left to pause".to_string());
+        }
+
+        Ok(KeyBindings { bindings })
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings.iter()
+            .find(|(_action, keys)| keys.contains(&key))
+            .map(|(action, _keys)| *action)
+    }
+
+    /// The keys bound to the action, as they should be shown to the player, e.g. "Left/a".
+    pub fn keys(&self, action: Action) -> String {
+        self.bindings.iter()
+            .find(|(a, _keys)| *a == action)
+            .map(|(_action, keys)| keys.iter().map(|key| key_name(*key)).collect::<Vec<_>>().join("/"))
+            .unwrap_or_default()
+    }
+
+    /// Pairs of keys and what they do, for every bound action.
+    pub fn controls(&self) -> Vec<(String, &'static str)> {
+        self.bindings.iter()
+            .filter(|(_action, keys)| !keys.is_empty())
+            .map(|(action, _keys)| (self.keys(*action), action.description()))
+            .collect()
+    }
+
+    fn file() -> io::Result<PathBuf> {
+        config_file(CONFIG_FILE)
+    }
+}
+
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Left => "Left".to_string(),
+        Key::Right => "Right".to_string(),
+        Key::Up => "Up".to_string(),
+        Key::Down => "Down".to_string(),
+        Key::Home => "Home".to_string(),
+        Key::End => "End".to_string(),
+        Key::PageUp => "PageUp".to_string(),
+        Key::PageDown => "PageDown".to_string(),
+        Key::Backspace => "Backspace".to_string(),
+        Key::Delete => "Delete".to_string(),
+        Key::Insert => "Insert".to_string(),
+        Key::Esc => "Esc".to_string(),
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char('\n') => "Enter".to_string(),
+        Key::Char('\t') => "Tab".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::F(n) => format!("F{}", n),
+        Key::Alt(c) => format!("Alt+{}", c),
+        Key::Ctrl(c) => format!("Ctrl+{}", c),
+        _ => "?".to_string(),
+    }
+}
+
+pub fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Char(c));
+    }
+
+    if let Some(c) = single_char(name, "Alt+") {
+        return Some(Key::Alt(c));
+    }
+
+    if let Some(c) = single_char(name, "Ctrl+") {
+        return Some(Key::Ctrl(c.to_ascii_lowercase()));
+    }
+
+    if let Some(n) = name.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+        return Some(Key::F(n));
+    }
+
+    let key = match name.to_lowercase().as_str() {
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        "insert" => Key::Insert,
+        "esc" => Key::Esc,
+        "space" => Key::Char(' '),
+        "enter" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        _ => return None
+    };
+
+    Some(key)
+}
+
+fn single_char(name: &str, prefix: &str) -> Option<char> {
+    let mut chars = name.strip_prefix(prefix)?.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None
+    }
+}
+
+#[test]
+fn key_names_should_be_parsed_back() {
+    for key in vec!(Key::Left, Key::Esc, Key::Char(' '), Key::Char('\n'), Key::Char('a'), Key::Char('+'),
+                    Key::F(5), Key::Alt('x'), Key::Ctrl('c')) {
+        assert_eq!(Some(key), parse_key(&key_name(key)));
+    }
+
+    assert_eq!(Some(Key::Left), parse_key("left"));
+    assert_eq!(None, parse_key("Nothing"));
+}
+
+#[test]
+fn config_should_override_the_defaults_of_the_game_only() {
+    let defaults = vec!((Action::MoveLeft, vec!(Key::Left)), (Action::Fire, vec!(Key::Char(' '))));
+    let config: Config = serde_json::from_str(r#"{
+        "spaceinvaders": { "MoveLeft": ["a", "Space"] },
+        "tetris": { "Fire": ["x"] }
+    }"#).unwrap();
+
+    let bindings = KeyBindings::new(defaults).with_config("Space Invaders", &config).unwrap();
+
+    assert_eq!(Some(Action::MoveLeft), bindings.action(Key::Char('a')));
+    assert_eq!(Some(Action::MoveLeft), bindings.action(Key::Char(' ')));
+    assert_eq!(None, bindings.action(Key::Left));
+    assert_eq!(None, bindings.action(Key::Char('x')));
+    assert_eq!(Some(Action::Pause), bindings.action(Key::Esc));
+    assert_eq!(vec!(("a/Space".to_string(), "move left"), ("p/Esc".to_string(), "pause")), bindings.controls());
+}
+
+#[test]
+fn given_no_key_left_to_pause_then_config_should_fail() {
+    let defaults = vec!((Action::MoveLeft, vec!(Key::Left)));
+    let config: Config = serde_json::from_str(r#"{ "tetris": { "MoveLeft": ["p", "Esc"] } }"#).unwrap();
+
+    assert!(KeyBindings::new(defaults).with_config("Tetris", &config).is_err());
+}
+
+#[test]
+fn given_an_unknown_key_then_config_should_fail() {
+    let config: Config = serde_json::from_str(r#"{ "tetris": { "Fire": ["Nothing"] } }"#).unwrap();
+
+    assert!(KeyBindings::new(Vec::new()).with_config("Tetris", &config).is_err());
+}
@@ -1,24 +1,57 @@
 use std::{io, thread};
+use std::cmp::{max, min};
 use std::io::{Read, Write};
 use std::time::Duration;
 
 use termion::color;
-use termion::event::Key;
+use termion::event::{Event, Key, MouseButton, MouseEvent};
 use termion::input::TermRead;
 
-pub fn choose<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, menu: &Vec<&str>, x: u16, y: u16) -> io::Result<Option<u8>> {
-    let mut index: i8 = 0;
+use crate::common::consolecolor::Color;
+use crate::common::glyphs;
+use crate::common::ioutils::{clear_rec, print_border, wait_for_any_key_async};
+use crate::common::terminal;
+use crate::common::theme;
+use crate::common::theme::Role;
+
+pub fn choose<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, menu: &[&str], x: u16, y: u16) -> io::Result<Option<u8>> {
+    choose_from(stdout, stdin, menu, 0, x, y)
+}
+
+/// Like `choose`, starting on the `selected` item.
+pub fn choose_from<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, menu: &[&str], selected: u8, x: u16, y: u16)
+                                      -> io::Result<Option<u8>> {
+    match choose_until_idle(stdout, stdin, menu, selected, x, y, None)? {
+        Choice::Item(index) => Ok(Some(index)),
+        _ => Ok(None)
+    }
+}
+
+/// How a menu was left.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Choice {
+    Item(u8),
+    /// With Esc.
+    Cancelled,
+    /// No key was pressed for a while, when the item at the index was selected.
+    Idle(u8),
+}
+
+/// Like `choose_from`, but gives up once no key was pressed for `idle`, if any.
+pub fn choose_until_idle<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, menu: &[&str], selected: u8, x: u16, y: u16,
+                                            idle: Option<Duration>) -> io::Result<Choice> {
+    const POLL: Duration = Duration::from_millis(50);
+
+    let mut index = selected as i8;
 
     'outer: loop {
-        for i in 0..menu.len() {
+        for (i, menu_item) in menu.iter().enumerate() {
             write!(stdout,
                    "{}",
                    termion::cursor::Goto(x, y + i as u16))?;
 
-            let menu_item = menu[i];
-
             if index == i as i8 {
-                write!(stdout, "{}{} ", color::Bg(color::Cyan), color::Fg(color::LightWhite))?;
+                write!(stdout, "{} ", Role::Selected)?;
             } else {
                 write!(stdout, "{} ", termion::style::Reset)?;
             }
@@ -30,8 +63,22 @@ pub fn choose<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, menu: &Vec<&str>
 
         stdout.flush()?;
 
+        let mut waited = Duration::from_millis(0);
+
         loop {
-            if let Some(Ok(c)) = stdin.keys().next() {
+            if let Some(event) = stdin.events().next() {
+                let c = match event? {
+                    Event::Key(c) => c,
+                    Event::Mouse(MouseEvent::Press(MouseButton::Left, mx, my)) => match clicked(menu, x, y, mx, my) {
+                        Some(clicked) => {
+                            index = clicked as i8;
+                            break 'outer;
+                        }
+                        None => continue
+                    },
+                    _ => continue
+                };
+
                 match c {
                     Key::Up => {
                         index -= 1;
@@ -48,16 +95,423 @@ pub fn choose<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, menu: &Vec<&str>
                         break;
                     }
                     Key::Esc => {
-                        return Result::Ok(None);
+                        return Result::Ok(Choice::Cancelled);
                     }
                     Key::Char('\n') => break 'outer,
                     _ => break
                 };
             }
 
-            thread::sleep(Duration::from_millis(50));
+            if idle.map(|idle| waited >= idle).unwrap_or(false) {
+                return Ok(Choice::Idle(index as u8));
+            }
+
+            thread::sleep(POLL);
+            waited += POLL;
+        }
+    }
+
+    Result::Ok(Choice::Item(index as u8))
+}
+
+/// The item under a click at mx and my, if any.
+fn clicked(menu: &[&str], x: u16, y: u16, mx: u16, my: u16) -> Option<usize> {
+    let index = my.checked_sub(y)? as usize;
+
+    // each item is drawn after a space
+    menu.get(index).filter(|item| mx >= x && mx <= x + item.chars().count() as u16).map(|_| index)
+}
+
+/// Top left corner of a box of the given size, in the center of the terminal.
+pub fn centered(width: u16, height: u16) -> (u16, u16) {
+    let (terminal_width, terminal_height) = terminal::size();
+    (terminal_width.saturating_sub(width) / 2 + 1, terminal_height.saturating_sub(height) / 2 + 1)
+}
+
+/// A cleared box with a border and a title; the content starts at x + 3, y + 3.
+pub fn print_box<W: Write>(stdout: &mut W, x: u16, y: u16, width: u16, height: u16, title: &str) -> io::Result<()> {
+    clear_rec(stdout, x, y, width, height)?;
+    print_border(stdout, x, y, width, height)?;
+
+    write!(stdout, "{}{}{}{}",
+           termion::cursor::Goto(x + 3, y + 1),
+           Role::Title,
+           title,
+           termion::style::Reset)
+}
+
+/// Asks a yes or no question, in a box in the center of the terminal. Esc answers no.
+pub fn confirm<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, question: &str) -> io::Result<bool> {
+    let width = max(question.len() as u16, 10) + 6;
+    let (x, y) = centered(width, 6);
+
+    print_box(stdout, x, y, width, 6, question)?;
+
+    Ok(choose(stdout, stdin, &["No", "Yes"], x + 3, y + 3)? == Some(1))
+}
+
+/// Shows some lines in a box in the center of the terminal, until a key is pressed.
+pub fn message<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, title: &str, lines: &[String]) -> io::Result<()> {
+    let width = lines.iter().map(|line| line.chars().count()).chain(vec!(title.len(), 13)).max().unwrap() as u16 + 6;
+    let height = lines.len() as u16 + 6;
+    let (x, y) = centered(width, height);
+
+    print_box(stdout, x, y, width, height, title)?;
+
+    for (i, line) in lines.iter().enumerate() {
+        write!(stdout, "{}{}", termion::cursor::Goto(x + 3, y + 3 + i as u16), line)?;
+    }
+
+    write!(stdout, "{}Press any key", termion::cursor::Goto(x + 3, y + 4 + lines.len() as u16))?;
+
+    stdout.flush()?;
+
+    wait_for_any_key_async(stdin).map(|_| ())
+}
+
+/// A line of text being edited.
+struct TextInput {
+    text: Vec<char>,
+    cursor: usize,
+    max_len: usize,
+}
+
+impl TextInput {
+    fn new(text: &str, max_len: usize) -> TextInput {
+        let text: Vec<char> = text.chars().take(max_len).collect();
+        TextInput { cursor: text.len(), text, max_len }
+    }
+
+    fn edit(&mut self, key: Key) {
+        match key {
+            Key::Char(c) if !c.is_control() && self.text.len() < self.max_len => {
+                self.text.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.cursor);
+            }
+            Key::Delete if self.cursor < self.text.len() => {
+                self.text.remove(self.cursor);
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = min(self.cursor + 1, self.text.len()),
+            Key::Home | Key::Ctrl('a') => self.cursor = 0,
+            Key::End | Key::Ctrl('e') => self.cursor = self.text.len(),
+            Key::Ctrl('u') => {
+                self.text.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+}
+
+/// Asks for a line of text of at most `max_len` characters, starting from `text`. Esc gives None.
+pub fn input<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, title: &str, text: &str, max_len: usize) -> io::Result<Option<String>> {
+    let width = max(title.len(), max_len + 1) as u16 + 6;
+    let (x, y) = centered(width, 5);
+    let mut input = TextInput::new(text, max_len);
+
+    print_box(stdout, x, y, width, 5, title)?;
+
+    loop {
+        write!(stdout, "{}{}", termion::cursor::Goto(x + 3, y + 3), Role::Selected)?;
+
+        for i in 0..=max_len {
+            let c = input.text.get(i).cloned().unwrap_or(' ');
+
+            if i == input.cursor {
+                write!(stdout, "{}{}{}{}{}", termion::style::Reset, Role::Cursor, c, termion::style::Reset, Role::Selected)?;
+            } else {
+                write!(stdout, "{}", c)?;
+            }
+        }
+
+        write!(stdout, "{}", termion::style::Reset)?;
+        stdout.flush()?;
+
+        match wait_for_any_key_async(stdin)? {
+            Key::Char('\n') => return Ok(Some(input.text())),
+            Key::Esc => return Ok(None),
+            key => input.edit(key)
         }
     }
+}
+
+/// The first item shown, so that the selected one is among the `rows` shown, moving as little as possible.
+fn scroll(top: usize, selected: usize, rows: usize) -> usize {
+    if selected < top {
+        selected
+    } else if selected >= top + rows {
+        selected + 1 - rows
+    } else {
+        top
+    }
+}
+
+/// Lets the player pick an item of a list that may be longer than the `rows` shown. Esc gives None.
+pub fn list<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, title: &str, items: &[String], rows: u16) -> io::Result<Option<usize>> {
+    let rows = max(min(rows as usize, items.len()), 1);
+    let item_width = items.iter().map(|item| item.chars().count()).max().unwrap_or(0);
+    let width = max(title.len(), item_width + 2) as u16 + 6;
+    let height = rows as u16 + 5;
+    let (x, y) = centered(width, height);
+    let mut selected = 0;
+    let mut top = 0;
+
+    print_box(stdout, x, y, width, height, title)?;
+
+    loop {
+        top = scroll(top, selected, rows);
+
+        for row in 0..rows {
+            write!(stdout, "{}", termion::cursor::Goto(x + 3, y + 3 + row as u16))?;
+
+            if let Some(item) = items.get(top + row) {
+                if top + row == selected {
+                    write!(stdout, "{}", Role::Selected)?;
+                }
+                write!(stdout, " {:<width$}{}", item, termion::style::Reset, width = item_width)?;
+            }
+        }
+
+        // arrows on the border tell that there is more to scroll to
+        let (paint, blank) = if glyphs::current().unicode() {
+            (color::Fg(Color::Border).to_string(), '│')
+        } else {
+            (Role::Border.to_string(), theme::current().blank(Role::Border))
+        };
+
+        write!(stdout, "{}{}{}{}{}{}",
+               paint,
+               termion::cursor::Goto(x + width - 1, y + 2),
+               if top > 0 { '^' } else { blank },
+               termion::cursor::Goto(x + width - 1, y + 3 + rows as u16),
+               if top + rows < items.len() { 'v' } else { blank },
+               termion::style::Reset)?;
+
+        stdout.flush()?;
+
+        let last = items.len().saturating_sub(1);
+
+        match wait_for_any_key_async(stdin)? {
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected = min(selected + 1, last),
+            Key::PageUp => selected = selected.saturating_sub(rows),
+            Key::PageDown => selected = min(selected + rows, last),
+            Key::Home => selected = 0,
+            Key::End => selected = last,
+            Key::Char('\n') if !items.is_empty() => return Ok(Some(selected)),
+            Key::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// A bar for each label, the longest `width` cells long.
+pub fn bar_chart<W: Write>(stdout: &mut W, x: u16, y: u16, bars: &[(String, u32)], width: u16) -> io::Result<()> {
+    let label_width = bars.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+    let highest = bars.iter().map(|(_, value)| *value).max().unwrap_or(0).max(1);
+
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let length = bar_length(*value, highest, width);
+
+        write!(stdout, "{}{:<label_width$} {}{}{} {}",
+               termion::cursor::Goto(x, y + i as u16),
+               label,
+               Role::Bar,
+               theme::current().blank(Role::Bar).to_string().repeat(length as usize),
+               termion::style::Reset,
+               value,
+               label_width = label_width)?;
+    }
+
+    Ok(())
+}
+
+/// Rounded up, so any value shows.
+fn bar_length(value: u32, highest: u32, width: u16) -> u16 {
+    ((u64::from(value) * u64::from(width)).div_ceil(u64::from(highest))) as u16
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingValue {
+    Check(bool),
+    /// None is shown as "default", and comes before the minimum.
+    Slider { value: Option<u32>, min: u32, max: u32 },
+    Text(String),
+    /// One of the options, which Left and Right go through.
+    Choice { options: Vec<&'static str>, index: usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Setting {
+    pub label: &'static str,
+    pub value: SettingValue,
+}
+
+impl SettingValue {
+    /// Left, Right or Space.
+    fn change(&mut self, key: Key) {
+        match self {
+            SettingValue::Check(checked) => if let Key::Left | Key::Right | Key::Char(' ') = key {
+                *checked = !*checked;
+            },
+            SettingValue::Slider { value, min, max } => *value = match (key, *value) {
+                (Key::Right, None) => Some(*min),
+                (Key::Right, Some(value)) => Some((value + 1).min(*max)),
+                (Key::Left, Some(value)) if value > *min => Some(value - 1),
+                (Key::Left, _) => None,
+                (_, value) => value
+            },
+            SettingValue::Choice { options, index } => match key {
+                Key::Right => *index = (*index + 1) % options.len(),
+                Key::Left => *index = (*index + options.len() - 1) % options.len(),
+                _ => {}
+            },
+            SettingValue::Text(_) => {}
+        }
+    }
+
+    fn print(&self) -> String {
+        const SLIDER_WIDTH: u32 = 10;
+
+        match self {
+            SettingValue::Check(checked) => format!("[{}]", if *checked { 'x' } else { ' ' }),
+            SettingValue::Slider { value: None, .. } => format!("[{}] default", "-".repeat(SLIDER_WIDTH as usize)),
+            SettingValue::Slider { value: Some(value), min, max } => {
+                let filled = (value - min) * SLIDER_WIDTH / (max - min).max(1);
+                format!("[{}{}] {}", "=".repeat(filled as usize), "-".repeat((SLIDER_WIDTH - filled) as usize), value)
+            }
+            SettingValue::Text(text) => text.clone(),
+            SettingValue::Choice { options, index } => format!("< {} >", options[*index])
+        }
+    }
+}
+
+/// Lets the player change some settings. Enter keeps the changes and gives true, Esc undoes them and gives false.
+/// Enter on a text setting asks for the new text instead.
+pub fn settings<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, title: &str, settings: &mut [Setting]) -> io::Result<bool> {
+    const VALUE_WIDTH: usize = 22;
+
+    let original = settings.to_vec();
+    let label_width = settings.iter().map(|setting| setting.label.len()).max().unwrap_or(0) + 2;
+    let width = max(title.len(), label_width + VALUE_WIDTH) as u16 + 6;
+    let height = settings.len() as u16 + 7;
+    let mut selected = 0;
+
+    loop {
+        let (x, y) = centered(width, height);
+
+        print_box(stdout, x, y, width, height, title)?;
+
+        for (i, setting) in settings.iter().enumerate() {
+            write!(stdout, "{}", termion::cursor::Goto(x + 3, y + 3 + i as u16))?;
+
+            if i == selected {
+                write!(stdout, "{}", Role::Selected)?;
+            }
+
+            write!(stdout, "{:<label_width$}{:<value_width$}{}", setting.label, setting.value.print(), termion::style::Reset,
+                   label_width = label_width, value_width = VALUE_WIDTH)?;
+        }
+
+        write!(stdout, "{}Enter to keep, Esc to cancel", termion::cursor::Goto(x + 3, y + 4 + settings.len() as u16))?;
+
+        stdout.flush()?;
+
+        match wait_for_any_key_async(stdin)? {
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected = min(selected + 1, settings.len() - 1),
+            Key::Char('\n') => match settings[selected].value {
+                SettingValue::Text(ref text) => {
+                    if let Some(text) = input(stdout, stdin, settings[selected].label, text, VALUE_WIDTH - 1)? {
+                        settings[selected].value = SettingValue::Text(text);
+                    }
+                }
+                _ => return Ok(true)
+            },
+            Key::Esc => {
+                settings.clone_from_slice(&original);
+                return Ok(false);
+            }
+            key => settings[selected].value.change(key)
+        }
+    }
+}
+
+#[test]
+fn text_input_should_edit_at_the_cursor() {
+    let mut input = TextInput::new("ac", 4);
+
+    input.edit(Key::Left);
+    input.edit(Key::Char('b'));
+    input.edit(Key::End);
+    input.edit(Key::Char('d'));
+    input.edit(Key::Char('e'));
+    assert_eq!("abcd", input.text());
+
+    input.edit(Key::Home);
+    input.edit(Key::Delete);
+    input.edit(Key::Right);
+    input.edit(Key::Backspace);
+    assert_eq!("cd", input.text());
+}
+
+#[test]
+fn list_should_scroll_only_to_show_the_selected_item() {
+    assert_eq!(0, scroll(0, 4, 5));
+    assert_eq!(1, scroll(0, 5, 5));
+    assert_eq!(3, scroll(5, 3, 5));
+    assert_eq!(5, scroll(5, 9, 5));
+}
+
+#[test]
+fn bars_should_be_scaled_to_the_highest_value() {
+    assert_eq!(20, bar_length(8, 8, 20));
+    assert_eq!(10, bar_length(4, 8, 20));
+    assert_eq!(1, bar_length(1, 100, 20));
+    assert_eq!(0, bar_length(0, 8, 20));
+}
+
+#[test]
+fn sliders_should_go_from_default_to_their_maximum() {
+    let mut slider = SettingValue::Slider { value: None, min: 4, max: 5 };
+
+    slider.change(Key::Right);
+    assert_eq!(SettingValue::Slider { value: Some(4), min: 4, max: 5 }, slider);
+    slider.change(Key::Right);
+    slider.change(Key::Right);
+    assert_eq!("[==========] 5", slider.print());
+    slider.change(Key::Left);
+    slider.change(Key::Left);
+    assert_eq!("[----------] default", slider.print());
+}
+
+#[test]
+fn choices_should_go_round_their_options() {
+    let mut choice = SettingValue::Choice { options: vec!("Dark", "Light", "Monochrome"), index: 0 };
+
+    choice.change(Key::Left);
+    assert_eq!("< Monochrome >", choice.print());
+    choice.change(Key::Right);
+    choice.change(Key::Right);
+    assert_eq!("< Light >", choice.print());
+}
+
+#[test]
+fn a_click_should_be_on_an_item_only_if_it_is_on_its_text() {
+    let menu = ["Tetris", "Snake"];
 
-    Result::Ok(Some(index as u8))
-}
\ No newline at end of file
+    assert_eq!(Some(0), clicked(&menu, 3, 5, 3, 5));
+    assert_eq!(Some(1), clicked(&menu, 3, 5, 8, 6));
+    assert_eq!(None, clicked(&menu, 3, 5, 9, 6));
+    assert_eq!(None, clicked(&menu, 3, 5, 4, 4));
+    assert_eq!(None, clicked(&menu, 3, 5, 4, 7));
+}
@@ -1,6 +1,27 @@
+pub mod canvas;
 pub mod consolecolor;
+pub mod difficulty;
+pub mod gameloop;
+pub mod gamemain;
+pub mod glyphs;
 pub mod grid;
+pub mod help;
+#[cfg(test)]
+pub mod headless;
+pub mod keys;
 pub mod menu;
+pub mod options;
+pub mod pause;
 pub mod persistence;
 pub mod point;
+pub mod random;
+pub mod replay;
+pub mod result;
+pub mod savegame;
+pub mod screen;
+pub mod session;
+pub mod stats;
+pub mod telnet;
+pub mod terminal;
+pub mod theme;
 pub mod ioutils;
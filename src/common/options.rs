@@ -0,0 +1,28 @@
+use crate::common::difficulty::Difficulty;
+
+/// The largest board side: the games keep their coordinates in an i8.
+pub const MAX_SIZE: u8 = 127;
+
+/// Per-game options, given on the command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GameOptions {
+    pub width: Option<u8>,
+    pub height: Option<u8>,
+    pub seed: Option<u64>,
+    pub difficulty: Difficulty,
+}
+
+impl GameOptions {
+    /// The requested board size, or the game's default, between the game's minimum and `MAX_SIZE`.
+    pub fn size(&self, default: (u8, u8), min: (u8, u8)) -> (u8, u8) {
+        let width = self.width.unwrap_or(default.0).clamp(min.0, MAX_SIZE);
+        let height = self.height.unwrap_or(default.1).clamp(min.1, MAX_SIZE);
+        (width, height)
+    }
+}
+
+#[test]
+fn the_size_should_stay_within_the_coordinates_of_the_games() {
+    assert_eq!((MAX_SIZE, 10), GameOptions::default().size((160, 10), (15, 10)));
+    assert_eq!((15, 12), GameOptions { width: Some(3), height: Some(12), ..GameOptions::default() }.size((80, 40), (15, 10)));
+}
@@ -0,0 +1,46 @@
+use std::io;
+use std::io::{Read, Write};
+
+use crate::common::help::Help;
+use crate::common::menu;
+use crate::common::terminal;
+
+const WIDTH: u16 = 36;
+const HEIGHT: u16 = 9;
+const HELP_ROWS: u16 = 12;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PauseChoice {
+    Resume,
+    Restart,
+    SaveAndQuit,
+    Quit,
+}
+
+/// Shows the pause menu over the game, until the player chooses how to go on.
+pub fn pause_menu<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, help: &Help) -> io::Result<PauseChoice> {
+    loop {
+        let (x, y) = menu::centered(WIDTH, HEIGHT);
+
+        menu::print_box(stdout, x, y, WIDTH, HEIGHT, "Paused")?;
+
+        let mut items = vec!["Resume", "Restart", "Help", "Save and quit", "Quit to menu"];
+
+        // a remote player would share the save slot with everyone else
+        if terminal::is_remote() {
+            items.retain(|item| *item != "Save and quit");
+        }
+
+        match menu::choose(stdout, stdin, &items, x + 3, y + 3)?.map(|index| items[index as usize]) {
+            None | Some("Resume") => return Ok(PauseChoice::Resume),
+            Some("Restart") => if menu::confirm(stdout, stdin, "Restart the game?")? {
+                return Ok(PauseChoice::Restart);
+            },
+            Some("Help") => menu::list(stdout, stdin, "Help", &help.lines(), HELP_ROWS).map(|_| ())?,
+            Some("Save and quit") => return Ok(PauseChoice::SaveAndQuit),
+            _ => if menu::confirm(stdout, stdin, "Quit to menu?")? {
+                return Ok(PauseChoice::Quit);
+            }
+        }
+    }
+}
@@ -1,28 +1,111 @@
 use std::{fs, io};
 use std::cmp::Ordering::Equal;
-use std::fs::create_dir_all;
-use std::fs::File;
-use std::io::{Error, ErrorKind, Read};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, File, OpenOptions};
+use std::io::{Error, ErrorKind, Write};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use chrono::{DateTime, Local};
-use dirs::home_dir;
+use dirs::{config_dir, data_dir, home_dir};
+use serde_json::Value;
+
+use crate::common::difficulty::Difficulty;
 
 const HIGH_SCORES_MAX_SIZE: usize = 10;
+/// Of the format of the high scores file; files of older versions are migrated when they are read.
+const VERSION: u64 = 1;
+/// Migrations from each older version to the next one, the first from the files without a version.
+const MIGRATIONS: [fn(&mut Value); VERSION as usize] = [add_player_and_game_details];
+const APP_DIR: &str = "consolegames";
+/// Redirects the data and config directories, as `--home` does.
+pub const HOME_VAR: &str = "CONSOLEGAMES_HOME";
+
+/// Which scores are the best in a table.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Order {
+    #[default]
+    HigherIsBetter,
+    LowerIsBetter,
+}
+
+/// What the scores of a table count.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Unit {
+    #[default]
+    Points,
+    Milliseconds,
+    Ticks,
+}
+
+/// How the scores of a table are ranked and shown, declared by its game.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Scoring {
+    pub order: Order,
+    pub unit: Unit,
+}
+
+impl Scoring {
+    pub const POINTS: Scoring = Scoring { order: Order::HigherIsBetter, unit: Unit::Points };
+
+    /// Whether `score` ranks before `other`.
+    pub fn better(&self, score: u32, other: u32) -> bool {
+        match self.order {
+            Order::HigherIsBetter => score > other,
+            Order::LowerIsBetter => score < other
+        }
+    }
+
+    pub fn format(&self, score: u32) -> String {
+        match self.unit {
+            Unit::Points => score.to_string(),
+            Unit::Milliseconds => format!("{}:{:02}.{:03}", score / 60_000, score / 1000 % 60, score % 1000),
+            Unit::Ticks => format!("{} ticks", score)
+        }
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct HighScores {
+    version: u64,
     path: String,
     entries: Vec<HighScore>,
+    #[serde(skip)]
+    scoring: Scoring,
+    /// Each difficulty has its own table, in its own file.
+    #[serde(skip)]
+    difficulty: Difficulty,
+    /// Where a corrupt file was moved, when `read` found one and started afresh.
+    #[serde(skip)]
+    backup: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct HighScore {
     score: u32,
     time: DateTime<Local>,
+    name: String,
+    mode: String,
+    /// In seconds of game time.
+    duration: u64,
+    stats: BTreeMap<String, u32>,
 }
 
 impl HighScore {
+    /// A score of a game that just ended, without a player name yet.
+    pub fn new(score: u32, mode: &str, duration: Duration, stats: &[(&str, u32)]) -> HighScore {
+        HighScore {
+            score,
+            time: Local::now(),
+            name: String::new(),
+            mode: mode.to_string(),
+            duration: duration.as_secs(),
+            stats: stats.iter().map(|(stat, value)| (stat.to_string(), *value)).collect(),
+        }
+    }
+
     pub fn score(&self) -> u32 {
         self.score
     }
@@ -30,143 +113,361 @@ impl HighScore {
     pub fn time(&self) -> DateTime<Local> {
         self.time
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration)
+    }
+
+    pub fn stats(&self) -> &BTreeMap<String, u32> {
+        &self.stats
+    }
 }
 
 impl HighScores {
-    pub fn read(path: &str) -> io::Result<HighScores> {
-        let file = HighScores::file(path.to_string())?;
+    fn new(path: &str, difficulty: Difficulty, scoring: Scoring) -> HighScores {
+        HighScores { version: VERSION, path: path.to_string(), entries: Vec::new(), scoring, difficulty, backup: None }
+    }
+
+    /// The table of a difficulty. A corrupt file is moved aside, see `backup`, and the scores start afresh.
+    pub fn read(path: &str, difficulty: Difficulty, scoring: Scoring) -> io::Result<HighScores> {
+        let file = HighScores::file(path, difficulty)?;
 
         if !file.exists() {
-            return Ok(HighScores { path: path.to_string(), entries: Vec::new() });
+            return Ok(HighScores::new(path, difficulty, scoring));
         }
 
-        let mut file = File::open(file)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        let scores_r = serde_json::from_str(&contents);
+        let _lock = lock(&file)?;
 
-        match scores_r {
-            Ok(scores) => Ok(scores),
-            Err(e) => Err(e.into())
-        }
+        let mut scores = HighScores::read_file(path, &file)?;
+        scores.scoring = scoring;
+        scores.difficulty = difficulty;
+        scores.sort();
+
+        Ok(scores)
     }
 
-    pub fn save(&self) -> io::Result<()> {
-        let file = HighScores::file(self.path.clone())?;
+    /// Adds the scores that another terminal saved since they were read, then writes them.
+    pub fn save(&mut self) -> io::Result<()> {
+        let file = HighScores::file(&self.path, self.difficulty)?;
 
-        create_dir_all(&file.parent().unwrap())?;
+        create_dir_all(file.parent().unwrap())?;
 
-        let serialized_r = serde_json::to_string_pretty(self);
+        let _lock = lock(&file)?;
 
-        match serialized_r {
-            Ok(serialized) => {
-                fs::write(file, serialized).map_err(From::from)
+        if file.exists() {
+            for entry in HighScores::read_file(&self.path, &file)?.entries {
+                if !self.entries.contains(&entry) {
+                    self.entries.push(entry);
+                }
             }
-            Err(e) => Result::Err(Error::new(ErrorKind::Other, e.to_string()))
+            self.sort();
         }
+
+        let serialized = serde_json::to_string_pretty(self).map_err(|e| Error::other(e.to_string()))?;
+
+        write_atomically(&file, &serialized)
     }
 
-    pub fn add(&mut self, score: u32) -> Option<HighScore> {
-        let entry = HighScore { score, time: Local::now() };
+    /// Where the corrupt file found by `read` was moved, if any.
+    pub fn backup(&self) -> Option<&Path> {
+        self.backup.as_deref()
+    }
 
-        let e = entry.clone();
+    fn read_file(path: &str, file: &Path) -> io::Result<HighScores> {
+        let contents = fs::read_to_string(file)?;
 
-        self.entries.push(entry);
+        match HighScores::parse(&contents) {
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                let backup = file.with_extension(format!("{}.corrupt", Local::now().format("%Y%m%d-%H%M%S")));
+
+                fs::rename(file, &backup)?;
+
+                Ok(HighScores { backup: Some(backup), ..HighScores::new(path, Difficulty::default(), Scoring::default()) })
+            }
+            // older versions kept the scores in another directory
+            scores => scores.map(|scores| HighScores { path: path.to_string(), ..scores })
+        }
+    }
+
+    /// Fails with `ErrorKind::InvalidData` when the contents are corrupt.
+    fn parse(contents: &str) -> io::Result<HighScores> {
+        let invalid = |e: serde_json::Error| Error::new(ErrorKind::InvalidData, e.to_string());
 
-        let len = self.entries.len();
+        let mut value: Value = serde_json::from_str(contents).map_err(invalid)?;
 
-        self.entries.sort_by(|a, b| b.score().cmp(&a.score()));
+        let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
 
-        if len > HIGH_SCORES_MAX_SIZE {
-            self.entries.remove(len - 1);
+        if version > VERSION {
+            return Err(Error::other(format!("The high scores were saved by a newer version (format {})", version)));
         }
 
-        self.entries.iter().find(|s| s.time().cmp(&e.time()) == Equal)
-            .map(|s| s.clone())
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(&mut value);
+        }
+        value["version"] = VERSION.into();
+
+        serde_json::from_value(value).map_err(invalid)
+    }
+
+    pub fn add(&mut self, entry: HighScore) -> Option<HighScore> {
+        let e = entry.clone();
+
+        self.entries.push(entry);
+
+        self.sort();
+
+        self.entries.iter().find(|s| s.time().cmp(&e.time()) == Equal).cloned()
     }
 
     pub fn entries(&self) -> Vec<HighScore> {
         self.entries.to_vec()
     }
 
-    pub fn min(&self) -> u32 {
+    pub fn scoring(&self) -> Scoring {
+        self.scoring
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// The score to beat to make the table, once it is full.
+    pub fn worst(&self) -> Option<u32> {
         if self.entries.len() < HIGH_SCORES_MAX_SIZE {
-            return 0;
+            return None;
+        }
+
+        self.entries.last().map(|it| it.score())
+    }
+
+    /// Whether the score would make the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        self.worst().map(|worst| self.scoring.better(score, worst)).unwrap_or(true)
+    }
+
+    pub fn best(&self) -> Option<u32> {
+        self.entries.first().map(|it| it.score())
+    }
+
+    /// Best scores first, and only as many as the table holds.
+    fn sort(&mut self) {
+        match self.scoring.order {
+            Order::HigherIsBetter => self.entries.sort_by_key(|s| std::cmp::Reverse(s.score())),
+            Order::LowerIsBetter => self.entries.sort_by_key(|s| s.score())
         }
+        self.entries.truncate(HIGH_SCORES_MAX_SIZE);
+    }
+
+    fn file(path: &str, difficulty: Difficulty) -> io::Result<PathBuf> {
+        data_file(path, &format!("scores{}.json", difficulty.suffix()))
+    }
+}
+
+/// From the first format, with only the score and time of each entry.
+fn add_player_and_game_details(scores: &mut Value) {
+    if let Some(entries) = scores.get_mut("entries").and_then(Value::as_array_mut) {
+        for entry in entries.iter_mut().filter_map(Value::as_object_mut) {
+            entry.entry("name").or_insert_with(|| "".into());
+            entry.entry("mode").or_insert_with(|| "".into());
+            entry.entry("duration").or_insert_with(|| 0.into());
+            entry.entry("stats").or_insert_with(|| Value::Object(Default::default()));
+        }
+    }
+}
+
+/// Held while the high scores file is read or written, so that two terminals don't overwrite each other's scores.
+/// It is released when the returned file is dropped.
+pub fn lock(file: &Path) -> io::Result<File> {
+    let lock = OpenOptions::new().create(true).truncate(false).write(true).open(file.with_extension("lock"))?;
+
+    lock.lock()?;
+
+    Ok(lock)
+}
+
+/// Writes a temporary file next to the file then renames it, so that the file is never left half written.
+pub fn write_atomically(file: &Path, contents: &str) -> io::Result<()> {
+    let temp = file.with_extension("tmp");
+
+    let mut out = File::create(&temp)?;
+    out.write_all(contents.as_bytes())?;
+    out.sync_all()?;
+
+    fs::rename(temp, file)
+}
+
+/// The name the player typed for their last high score.
+pub fn last_name() -> io::Result<Option<String>> {
+    let file = directories()?.data.join("name");
+
+    if !file.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(fs::read_to_string(file)?.trim().to_string()))
+}
+
+pub fn save_last_name(name: &str) -> io::Result<()> {
+    let file = directories()?.data.join("name");
+
+    create_dir_all(file.parent().unwrap())?;
+
+    fs::write(file, name)
+}
+
+static HOME: OnceLock<PathBuf> = OnceLock::new();
+
+#[cfg(test)]
+thread_local! {
+    /// Replaces the home directory in headless tests, so they don't touch the player's files.
+    pub static TEST_HOME: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Keeps the data and config of the games in `home`, instead of the directories of the user; this wins over
+/// `HOME_VAR`. Must be called before anything is read.
+pub fn set_home(home: PathBuf) {
+    HOME.set(home).expect("the home directory is set once");
+}
+
+/// Where the games keep their files.
+struct Directories {
+    data: PathBuf,
+    config: PathBuf,
+    /// Where the games kept their files before, in a directory each, e.g. `~/.tetris`.
+    legacy: Option<PathBuf>,
+}
+
+/// `$XDG_DATA_HOME/consolegames` and `$XDG_CONFIG_HOME/consolegames`, or their platform equivalents, unless
+/// they are redirected: then they are the `data` and `config` directories of the given home.
+fn directories() -> io::Result<Directories> {
+    #[cfg(test)]
+    if let Some(home) = TEST_HOME.with(|home| home.borrow().clone()) {
+        // like a home directory, so that migrations can be tested too
+        return Ok(Directories { data: home.join("data"), config: home.join("config"), legacy: Some(home) });
+    }
+
+    if let Some(home) = HOME.get().cloned().or_else(|| env::var_os(HOME_VAR).map(PathBuf::from)) {
+        return Ok(Directories { data: home.join("data"), config: home.join("config"), legacy: None });
+    }
+
+    match (data_dir(), config_dir()) {
+        (Some(data), Some(config)) =>
+            Ok(Directories { data: data.join(APP_DIR), config: config.join(APP_DIR), legacy: home_dir() }),
+        _ => Err(Error::other("Impossible to get your data and config dirs!"))
+    }
+}
+
+/// A file in the directory of a game, e.g. `tetris`, under the data directory. The first time, the files of
+/// the game are moved there from where older versions kept them.
+pub fn data_file(path: &str, name: &str) -> io::Result<PathBuf> {
+    let directories = directories()?;
 
-        self.entries.last().map(|it| it.score()).unwrap_or(0)
+    let dir = directories.data.join(path);
+
+    if let Some(legacy) = directories.legacy.map(|home| home.join(format!(".{}", path))) {
+        // other programs may have a directory of the same name, but not with our scores in it
+        if !dir.exists() && legacy.join("scores.json").exists() {
+            migrate(&legacy, &dir)?;
+        }
     }
 
-    pub fn max(&self) -> u32 {
-        self.entries.first().map(|it| it.score()).unwrap_or(0)
+    Ok(dir.join(name))
+}
+
+/// A file in the config directory.
+pub fn config_file(name: &str) -> io::Result<PathBuf> {
+    Ok(directories()?.config.join(name))
+}
+
+/// Moves the files of the directory, or copies them when it can't be moved, e.g. to another file system.
+fn migrate(from: &Path, to: &Path) -> io::Result<()> {
+    create_dir_all(to.parent().unwrap())?;
+
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
     }
 
-    fn file(path: String) -> io::Result<PathBuf> {
-        let mut file = if let Some(home) = home_dir() {
-            home
-        } else {
-            return Result::Err(Error::new(ErrorKind::Other, "Impossible to get your home dir!"));
-        };
+    create_dir_all(to)?;
 
-        file.push(path);
-        file.push("scores.json");
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
 
-        Ok(file)
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
+fn score(score: u32) -> HighScore {
+    HighScore::new(score, "10x20", Duration::from_secs(60), &[])
+}
+
 #[test]
-fn given_empty_then_max_should_be_the_minimum_int_value() {
-    let hs = HighScores { path: ".test".to_string(), entries: Vec::new() };
+fn given_empty_then_there_should_be_no_best() {
+    let hs = HighScores::new("test", Difficulty::Normal, Scoring::POINTS);
 
-    assert_eq!(0, hs.max());
+    assert_eq!(None, hs.best());
 }
 
 #[test]
-fn given_empty_then_min_should_be_the_minimum_value() {
-    let hs = HighScores { path: ".test".to_string(), entries: Vec::new() };
+fn given_empty_then_there_should_be_no_worst() {
+    let hs = HighScores::new("test", Difficulty::Normal, Scoring::POINTS);
 
-    assert_eq!(0, hs.min());
+    assert_eq!(None, hs.worst());
 }
 
 #[test]
-fn given_not_empty_then_max_should_be_the_max_entry_even_when_inserted_in_the_inverse_order() {
-    let mut hs = HighScores { path: ".test".to_string(), entries: Vec::new() };
+fn given_not_empty_then_best_should_be_the_max_entry_even_when_inserted_in_the_inverse_order() {
+    let mut hs = HighScores::new("test", Difficulty::Normal, Scoring::POINTS);
 
-    hs.add(100);
-    hs.add(200);
+    hs.add(score(100));
+    hs.add(score(200));
 
-    assert_eq!(200, hs.max());
+    assert_eq!(Some(200), hs.best());
 }
 
 #[test]
-fn given_less_entries_then_max_size_then_min_should_be_the_minimum_int_value() {
-    let mut hs = HighScores { path: ".test".to_string(), entries: Vec::new() };
+fn given_less_entries_then_max_size_then_there_should_be_no_worst() {
+    let mut hs = HighScores::new("test", Difficulty::Normal, Scoring::POINTS);
 
-    hs.add(100);
+    hs.add(score(100));
 
-    assert_eq!(0, hs.min());
+    assert_eq!(None, hs.worst());
 }
 
 #[test]
-fn given_enough_entries_then_min_should_be_the_min_entry() {
-    let mut hs = HighScores { path: ".test".to_string(), entries: Vec::new() };
+fn given_enough_entries_then_worst_should_be_the_min_entry() {
+    let mut hs = HighScores::new("test", Difficulty::Normal, Scoring::POINTS);
 
     for i in 0..HIGH_SCORES_MAX_SIZE {
-        hs.add(i as u32 * 100);
+        hs.add(score(i as u32 * 100));
     }
 
-    assert_eq!(0, hs.min());
+    assert_eq!(Some(0), hs.worst());
 }
 
 #[test]
 fn entries_should_be_sorted_on_add() {
-    let mut hs = HighScores { path: ".test".to_string(), entries: Vec::new() };
+    let mut hs = HighScores::new("test", Difficulty::Normal, Scoring::POINTS);
 
-    hs.add(100);
-    hs.add(200);
-    hs.add(300);
+    hs.add(score(100));
+    hs.add(score(200));
+    hs.add(score(300));
 
     let entries = hs.entries();
     assert_eq!(300, entries[0].score());
@@ -176,10 +477,10 @@ fn entries_should_be_sorted_on_add() {
 
 #[test]
 fn given_more_entries_than_max_size_then_only_the_higher_entries_should_be_retained() {
-    let mut hs = HighScores { path: ".test".to_string(), entries: Vec::new() };
+    let mut hs = HighScores::new("test", Difficulty::Normal, Scoring::POINTS);
 
     for i in 0..HIGH_SCORES_MAX_SIZE + 5 {
-        hs.add(i as u32 * 100);
+        hs.add(score(i as u32 * 100));
     }
 
     let entries = hs.entries();
@@ -187,4 +488,136 @@ fn given_more_entries_than_max_size_then_only_the_higher_entries_should_be_retai
     assert_eq!(HIGH_SCORES_MAX_SIZE, entries.len());
 
     assert_eq!(entries.first().map(HighScore::score), Some(100 * (HIGH_SCORES_MAX_SIZE as u32 + 5 - 1)));
-}
\ No newline at end of file
+}
+
+#[test]
+fn a_score_should_qualify_until_the_table_is_full_and_then_only_above_the_minimum() {
+    let mut hs = HighScores::new("test", Difficulty::Normal, Scoring::POINTS);
+
+    for i in 1..=HIGH_SCORES_MAX_SIZE {
+        assert!(hs.qualifies(0));
+        hs.add(score(i as u32 * 100));
+    }
+
+    assert!(!hs.qualifies(100));
+    assert!(hs.qualifies(101));
+}
+
+#[test]
+fn when_lower_is_better_then_the_lowest_scores_should_be_retained_and_qualify() {
+    let mut hs = HighScores::new("test", Difficulty::Normal, Scoring { order: Order::LowerIsBetter, unit: Unit::Milliseconds });
+
+    for i in 1..=HIGH_SCORES_MAX_SIZE + 5 {
+        hs.add(score(i as u32 * 100));
+    }
+
+    assert_eq!(Some(100), hs.best());
+    assert_eq!(Some(HIGH_SCORES_MAX_SIZE as u32 * 100), hs.worst());
+    assert!(hs.qualifies(999));
+    assert!(!hs.qualifies(1000));
+}
+
+#[test]
+fn scores_should_be_formatted_in_their_unit() {
+    let scoring = |unit| Scoring { order: Order::HigherIsBetter, unit };
+
+    assert_eq!("1234", scoring(Unit::Points).format(1234));
+    assert_eq!("1:02.345", scoring(Unit::Milliseconds).format(62_345));
+    assert_eq!("20 ticks", scoring(Unit::Ticks).format(20));
+}
+
+#[test]
+fn scores_of_the_first_format_should_be_migrated() {
+    let json = r#"{"path": "test", "entries": [{"score": 300, "time": "2020-05-01T10:00:00+02:00"}]}"#;
+
+    let hs = HighScores::parse(json).unwrap();
+
+    assert_eq!(VERSION, hs.version);
+    let entry = &hs.entries()[0];
+    assert_eq!(300, entry.score());
+    assert_eq!("", entry.name());
+    assert_eq!(Duration::from_secs(0), entry.duration());
+    assert!(entry.stats().is_empty());
+}
+
+#[test]
+fn scores_of_a_newer_version_should_not_be_read() {
+    let json = r#"{"version": 1000, "path": "test", "entries": []}"#;
+
+    let error = HighScores::parse(json).unwrap_err();
+
+    assert_ne!(ErrorKind::InvalidData, error.kind());
+}
+
+#[cfg(test)]
+use crate::common::headless::{Headless, Script};
+
+#[test]
+fn a_corrupt_file_should_be_moved_aside_and_the_scores_start_afresh() {
+    let headless = Headless::new(80, 24);
+
+    let (hs, _) = headless.run(Script::new(), |_, _| {
+        let file = HighScores::file("test", Difficulty::Normal)?;
+        create_dir_all(file.parent().unwrap())?;
+        fs::write(&file, "{\"path\": \"test\", \"entr")?;
+
+        HighScores::read("test", Difficulty::Normal, Scoring::POINTS)
+    });
+
+    let hs = hs.unwrap();
+    assert!(hs.entries().is_empty());
+    assert_eq!("{\"path\": \"test\", \"entr", fs::read_to_string(hs.backup().unwrap()).unwrap());
+}
+
+#[test]
+fn scores_saved_meanwhile_by_another_terminal_should_be_kept() {
+    let headless = Headless::new(80, 24);
+
+    let (hs, _) = headless.run(Script::new(), |_, _| {
+        let mut first = HighScores::read("test", Difficulty::Normal, Scoring::POINTS)?;
+        let mut second = HighScores::read("test", Difficulty::Normal, Scoring::POINTS)?;
+
+        first.add(score(100));
+        first.save()?;
+        second.add(score(200));
+        second.save()?;
+
+        HighScores::read("test", Difficulty::Normal, Scoring::POINTS)
+    });
+
+    let scores: Vec<u32> = hs.unwrap().entries().iter().map(HighScore::score).collect();
+    assert_eq!(vec!(200, 100), scores);
+    assert!(!headless.home().join("data").join("test").join("scores.tmp").exists());
+}
+
+#[test]
+fn the_scores_of_older_versions_should_be_moved_to_the_data_directory() {
+    let headless = Headless::new(80, 24);
+    let legacy = headless.home().join(".test");
+    create_dir_all(&legacy).unwrap();
+    fs::write(legacy.join("scores.json"), r#"{"path": ".test", "entries": [{"score": 300, "time": "2020-05-01T10:00:00+02:00"}]}"#)
+        .unwrap();
+    fs::write(legacy.join("save.json"), "{}").unwrap();
+
+    let (hs, _) = headless.run(Script::new(), |_, _| HighScores::read("test", Difficulty::Normal, Scoring::POINTS));
+
+    assert_eq!(Some(300), hs.unwrap().best());
+    assert!(!legacy.exists());
+    assert!(headless.home().join("data").join("test").join("save.json").exists());
+}
+
+#[test]
+fn each_difficulty_should_have_its_own_table() {
+    let headless = Headless::new(80, 24);
+
+    let (hs, _) = headless.run(Script::new(), |_, _| {
+        let mut hard = HighScores::read("test", Difficulty::Hard, Scoring::POINTS)?;
+        hard.add(score(100));
+        hard.save()?;
+
+        HighScores::read("test", Difficulty::Normal, Scoring::POINTS)
+    });
+
+    assert_eq!(None, hs.unwrap().best());
+    assert!(headless.home().join("data").join("test").join("scores-hard.json").exists());
+}
@@ -1,4 +1,4 @@
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Point {
     pub x: i8,
     pub y: i8,
@@ -35,7 +35,7 @@ impl Point {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Pointf32 {
     pub x: f32,
     pub y: f32,
@@ -50,29 +50,13 @@ impl Pointf32 {
         Pointf32::new(self.x, self.y + 1.0)
     }
 
-    pub fn right(&self) -> Pointf32 {
-        Pointf32::new(self.x + 1.0, self.y)
-    }
-
-    pub fn left(&self) -> Pointf32 {
-        Pointf32::new(self.x - 1.0, self.y)
-    }
-
     pub fn up(&self) -> Pointf32 {
         Pointf32::new(self.x, self.y - 1.0)
     }
 
-    pub fn mv(&self, direction: &Direction) -> Pointf32 {
-        match direction {
-            Direction::North => self.up(),
-            Direction::South => self.down(),
-            Direction::East => self.right(),
-            Direction::West => self.left()
-        }
-    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Direction {
     North,
     South,
@@ -0,0 +1,97 @@
+use rand::{Error, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// The generator of a game core, so the same seed always produces the same game. It is the generator of `StdRng`,
+/// which can be saved: a continued game draws the numbers it would have drawn without the save.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "SavedRng", into = "SavedRng")]
+pub struct GameRng {
+    seed: u64,
+    words: u64,
+    rng: ChaCha20Rng,
+}
+
+/// What is saved of a generator: its seed and how many 32 bit words it has drawn since.
+#[derive(Serialize, Deserialize)]
+struct SavedRng {
+    seed: u64,
+    words: u64,
+}
+
+pub fn new_rng(seed: u64) -> GameRng {
+    GameRng { seed, words: 0, rng: ChaCha20Rng::seed_from_u64(seed) }
+}
+
+/// A seed for a game that is not given one, so it can be recorded and the game played again.
+pub fn new_seed() -> u64 {
+    rand::random()
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.words += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.words += 2;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.words += (dest.len() as u64).div_ceil(4);
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl From<SavedRng> for GameRng {
+    fn from(saved: SavedRng) -> GameRng {
+        let mut rng = new_rng(saved.seed);
+        rng.words = saved.words;
+        rng.rng.set_word_pos(u128::from(saved.words));
+        rng
+    }
+}
+
+impl From<GameRng> for SavedRng {
+    fn from(rng: GameRng) -> SavedRng {
+        SavedRng { seed: rng.seed, words: rng.words }
+    }
+}
+
+#[cfg(test)]
+use rand::Rng;
+
+#[cfg(test)]
+use rand::rngs::StdRng;
+
+#[test]
+fn given_a_seed_then_the_generator_should_draw_as_std_rng() {
+    let mut rng = new_rng(7);
+    let mut std_rng = StdRng::seed_from_u64(7);
+
+    for _ in 0..100 {
+        assert_eq!(rng.gen::<u64>(), std_rng.gen::<u64>());
+    }
+}
+
+#[test]
+fn given_a_saved_generator_then_it_should_go_on_as_without_the_save() {
+    let mut rng = new_rng(1);
+    for n in 0..37 {
+        // every way of drawing, to count each of them and to cross the blocks of the generator
+        if n % 3 == 0 { rng.next_u32(); } else { rng.next_u64(); }
+        rng.fill_bytes(&mut [0; 5]);
+    }
+
+    let mut resumed: GameRng = serde_json::from_str(&serde_json::to_string(&rng).unwrap()).unwrap();
+
+    for _ in 0..100 {
+        assert_eq!(resumed.gen::<u32>(), rng.gen::<u32>());
+    }
+}
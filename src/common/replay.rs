@@ -0,0 +1,81 @@
+use std::fs;
+use std::fs::create_dir_all;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::common::difficulty::Difficulty;
+use crate::common::keys::Action;
+use crate::common::options::GameOptions;
+use crate::common::persistence::data_file;
+
+/// An action of the player, at the number of ticks the game had run when it happened.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    pub tick: u64,
+    pub action: Action,
+}
+
+/// A recorded game. With the same size and seed, and the same actions at the same ticks, a game plays exactly
+/// the same again, however fast the ticks come.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub game: String,
+    pub width: u8,
+    pub height: u8,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    /// How many ticks the game lasted, so a replay stops where the game was quit.
+    pub ticks: u64,
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    pub fn read(file: &Path) -> io::Result<Replay> {
+        let contents = fs::read_to_string(file)?;
+
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", file.display(), e)))
+    }
+
+    pub fn write(&self, file: &Path) -> io::Result<()> {
+        if let Some(parent) = file.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let serialized = serde_json::to_string_pretty(self).map_err(|e| Error::other(e.to_string()))?;
+
+        fs::write(file, serialized)
+    }
+
+    /// Keeps this as the last game played, in the directory of the game, e.g. `tetris`.
+    pub fn write_last(&self, path: &str) -> io::Result<()> {
+        self.write(&data_file(path, "replay.json")?)
+    }
+
+    pub fn options(&self) -> GameOptions {
+        GameOptions { width: Some(self.width), height: Some(self.height), seed: Some(self.seed), difficulty: self.difficulty }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+    Normal,
+    Double,
+    /// A tick for every key pressed.
+    Step,
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Speed, String> {
+        match s {
+            "1" | "1x" => Ok(Speed::Normal),
+            "2" | "2x" => Ok(Speed::Double),
+            "step" => Ok(Speed::Step),
+            _ => Err(format!("Unknown speed {}", s))
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+/// Why a game ended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// The player died, or the game could not go on.
+    Lost,
+    /// The player did all there was to do: every brick or invader is gone, or the lines of a sprint are cleared.
+    Cleared,
+    Quit,
+    /// Quit, with the game saved to be continued later.
+    Saved,
+}
+
+/// What a game adds to the lifetime statistics of the player.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Counter {
+    /// Added up over all games, e.g. lines cleared.
+    Total(&'static str, u32),
+    /// The best of all games.
+    Longest(&'static str, u32),
+}
+
+/// How a game ended, returned by `Main::run` to rank and sum up the game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameResult {
+    pub outcome: Outcome,
+    /// None when the game can't be ranked, e.g. it was quit.
+    pub score: Option<u32>,
+    /// Game time, which doesn't count pauses.
+    pub duration: Duration,
+    pub mode: String,
+    pub stats: Vec<(&'static str, u32)>,
+    pub counters: Vec<Counter>,
+}
+
+impl GameResult {
+    pub fn new(outcome: Outcome, score: Option<u32>, duration: Duration, mode: String,
+               stats: Vec<(&'static str, u32)>) -> GameResult {
+        GameResult { outcome, score, duration, mode, stats, counters: Vec::new() }
+    }
+
+    pub fn counting(mut self, counters: Vec<Counter>) -> GameResult {
+        self.counters = counters;
+        self
+    }
+
+    /// A game that was quit, or saved, before it was over.
+    pub fn unfinished(outcome: Outcome) -> GameResult {
+        GameResult::new(outcome, None, Duration::from_secs(0), String::new(), Vec::new())
+    }
+
+    /// Whether the game was played until it was over, as opposed to quit or saved.
+    pub fn is_over(&self) -> bool {
+        self.outcome == Outcome::Lost || self.outcome == Outcome::Cleared
+    }
+}
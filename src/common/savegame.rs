@@ -0,0 +1,55 @@
+use std::fs;
+use std::fs::create_dir_all;
+use std::io;
+use std::io::{Error, ErrorKind};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::common::difficulty::Difficulty;
+use crate::common::persistence::data_file;
+
+/// The game in progress that the player saved to continue later, next to the game's scores.
+/// There is one slot per game and difficulty: saving again replaces it, and continuing empties it.
+pub struct SaveSlot {
+    path: &'static str,
+    name: String,
+}
+
+impl SaveSlot {
+    pub fn new(path: &'static str, difficulty: Difficulty) -> SaveSlot {
+        SaveSlot { path, name: format!("save{}.json", difficulty.suffix()) }
+    }
+
+    pub fn exists(&self) -> bool {
+        data_file(self.path, &self.name).map(|file| file.exists()).unwrap_or(false)
+    }
+
+    pub fn save<T: Serialize>(&self, game: &T) -> io::Result<()> {
+        let file = data_file(self.path, &self.name)?;
+
+        create_dir_all(file.parent().unwrap())?;
+
+        let serialized = serde_json::to_string(game).map_err(|e| Error::other(e.to_string()))?;
+
+        fs::write(file, serialized)
+    }
+
+    /// Takes the saved game out of the slot, if there is one.
+    pub fn take<T: DeserializeOwned>(&self) -> io::Result<Option<T>> {
+        let file = data_file(self.path, &self.name)?;
+
+        let contents = match fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e)
+        };
+
+        let game = serde_json::from_str(&contents)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", file.display(), e)))?;
+
+        fs::remove_file(file)?;
+
+        Ok(Some(game))
+    }
+}
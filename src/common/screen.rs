@@ -0,0 +1,225 @@
+use std::io;
+use std::io::Write;
+
+use termion::color;
+
+use crate::common::canvas::{Buffer, Canvas};
+use crate::common::consolecolor::Color;
+use crate::common::theme;
+
+/// Writing a few unchanged cells is cheaper than a cursor movement, which takes 6 to 8 bytes.
+const MAX_REWRITTEN_CELLS: u16 = 4;
+
+/// A back buffer the games draw into, as a canvas. `flush` writes to the terminal only the cells that changed since
+/// the previous flush. Coordinates start from 1, as in `termion::cursor::Goto`, and are moved by the origin,
+/// so a game can be drawn anywhere on the screen. Whatever is drawn outside of the screen is discarded.
+pub struct Screen {
+    width: u16,
+    height: u16,
+    origin: (u16, u16),
+    /// Width and height of what is drawn from the origin.
+    area: (u16, u16),
+    back: Buffer,
+    /// What is on the terminal.
+    front: Buffer,
+    invalid: bool,
+}
+
+impl Screen {
+    pub fn new(width: u16, height: u16) -> Screen {
+        Screen {
+            width,
+            height,
+            origin: (0, 0),
+            area: (width, height),
+            back: Buffer::new(width, height),
+            front: Buffer::new(width, height),
+            invalid: true,
+        }
+    }
+
+    /// A screen of the given size, where an area of `area_width` and `area_height` is drawn in the center.
+    pub fn centered(width: u16, height: u16, area_width: u16, area_height: u16) -> Screen {
+        let mut screen = Screen::new(width, height);
+        screen.origin = (width.saturating_sub(area_width) / 2, height.saturating_sub(area_height) / 2);
+        screen.area = (area_width, area_height);
+        screen
+    }
+
+    /// A position of the terminal in the coordinates of the area, if it is in it.
+    pub fn area_position(&self, x: u16, y: u16) -> Option<(u16, u16)> {
+        if x <= self.origin.0 || y <= self.origin.1 || x > self.origin.0 + self.area.0 || y > self.origin.1 + self.area.1 {
+            return None;
+        }
+
+        Some((x - self.origin.0, y - self.origin.1))
+    }
+
+    /// Clears the back buffer, to draw a new frame.
+    pub fn clear(&mut self) {
+        self.back = Buffer::new(self.width, self.height);
+    }
+
+    pub fn flush<W: Write>(&mut self, stdout: &mut W) -> io::Result<()> {
+        write!(stdout, "{}", termion::style::Reset)?;
+
+        if self.invalid {
+            write!(stdout, "{}", termion::clear::All)?;
+            self.front = Buffer::new(self.width, self.height);
+            self.invalid = false;
+        }
+
+        // without colors, blanks on a background are drawn with glyphs
+        let style = theme::current();
+        let mut cursor: Option<(u16, u16)> = None;
+        let mut fg = Color::DefaultColor;
+        let mut bg = Color::DefaultColor;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = self.back.cell(x + 1, y + 1);
+
+                if cell == self.front.cell(x + 1, y + 1) {
+                    continue;
+                }
+
+                match cursor {
+                    Some((cx, cy)) if cy == y && cx == x => {}
+                    Some((cx, cy)) if cy == y && cx < x && x - cx <= MAX_REWRITTEN_CELLS
+                        && self.same_colors(cx, x, y, fg, bg) => {
+                        for ix in cx..x {
+                            let rewritten = self.back.cell(ix + 1, y + 1);
+                            write!(stdout, "{}", style.glyph(rewritten.c, rewritten.bg))?;
+                        }
+                    }
+                    _ => write!(stdout, "{}", termion::cursor::Goto(x + 1, y + 1))?
+                }
+
+                if cell.fg != fg {
+                    write!(stdout, "{}", color::Fg(cell.fg))?;
+                    fg = cell.fg;
+                }
+
+                if cell.bg != bg {
+                    write!(stdout, "{}", color::Bg(cell.bg))?;
+                    bg = cell.bg;
+                }
+
+                write!(stdout, "{}", style.glyph(cell.c, cell.bg))?;
+                self.front.put(x + 1, y + 1, cell.c, cell.fg, cell.bg);
+
+                // after the last column the terminal may or may not wrap, so we don't know where the cursor is
+                cursor = if x + 1 < self.width { Some((x + 1, y)) } else { None };
+            }
+        }
+
+        write!(stdout, "{}", termion::style::Reset)?;
+
+        stdout.flush()
+    }
+
+    /// Whether the cells of the row, from `from` to `to` excluded, can be written again with the current colors.
+    fn same_colors(&self, from: u16, to: u16, y: u16, fg: Color, bg: Color) -> bool {
+        (from..to).all(|x| {
+            let cell = self.back.cell(x + 1, y + 1);
+            cell.fg == fg && cell.bg == bg
+        })
+    }
+}
+
+impl Canvas for Screen {
+    fn put(&mut self, x: u16, y: u16, c: char, fg: Color, bg: Color) {
+        if let (Some(x), Some(y)) = (x.checked_add(self.origin.0), y.checked_add(self.origin.1)) {
+            self.back.put(x, y, c, fg, bg);
+        }
+    }
+}
+
+#[cfg(test)]
+fn flushed(screen: &mut Screen) -> String {
+    let mut out = Vec::new();
+    screen.flush(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn given_nothing_changed_then_flush_should_write_no_cell() {
+    let mut screen = Screen::new(10, 5);
+    screen.text(1, 1, "Score", Color::DefaultColor, Color::DefaultColor);
+    flushed(&mut screen);
+
+    screen.clear();
+    screen.text(1, 1, "Score", Color::DefaultColor, Color::DefaultColor);
+
+    assert_eq!(format!("{}{}", termion::style::Reset, termion::style::Reset), flushed(&mut screen));
+}
+
+#[test]
+fn flush_should_write_only_changed_cells() {
+    let mut screen = Screen::new(10, 5);
+    screen.text(1, 1, "Score: 100", Color::DefaultColor, Color::DefaultColor);
+    flushed(&mut screen);
+
+    screen.clear();
+    screen.text(1, 1, "Score: 200", Color::DefaultColor, Color::DefaultColor);
+    screen.put(3, 3, '#', Color::Red, Color::DefaultColor);
+
+    assert_eq!(format!("{}{}2{}{}#{}",
+                       termion::style::Reset,
+                       termion::cursor::Goto(8, 1),
+                       termion::cursor::Goto(3, 3),
+                       color::Fg(Color::Red),
+                       termion::style::Reset),
+               flushed(&mut screen));
+}
+
+#[test]
+fn flush_should_rewrite_near_cells_instead_of_moving_the_cursor() {
+    let mut screen = Screen::new(10, 5);
+    screen.text(1, 1, "abcdef", Color::DefaultColor, Color::DefaultColor);
+    flushed(&mut screen);
+
+    screen.clear();
+    screen.text(1, 1, "Abcdeg", Color::DefaultColor, Color::DefaultColor);
+
+    assert_eq!(format!("{}{}Abcdeg{}", termion::style::Reset, termion::cursor::Goto(1, 1), termion::style::Reset),
+               flushed(&mut screen));
+}
+
+#[test]
+fn drawing_outside_of_the_screen_should_be_discarded() {
+    let mut screen = Screen::new(3, 2);
+    screen.text(2, 2, "abc", Color::DefaultColor, Color::DefaultColor);
+    screen.put(0, 0, 'x', Color::DefaultColor, Color::DefaultColor);
+
+    assert_eq!(format!("{}{}{}ab{}",
+                       termion::style::Reset,
+                       termion::clear::All,
+                       termion::cursor::Goto(2, 2),
+                       termion::style::Reset),
+               flushed(&mut screen));
+}
+
+#[test]
+fn given_a_centered_screen_then_drawing_should_be_moved_to_the_center() {
+    let mut screen = Screen::centered(10, 5, 4, 1);
+    screen.text(1, 1, "abcd", Color::DefaultColor, Color::DefaultColor);
+
+    assert_eq!(format!("{}{}{}abcd{}",
+                       termion::style::Reset,
+                       termion::clear::All,
+                       termion::cursor::Goto(4, 3),
+                       termion::style::Reset),
+               flushed(&mut screen));
+}
+
+#[test]
+fn only_positions_in_the_area_should_be_in_its_coordinates() {
+    let screen = Screen::centered(10, 5, 4, 1);
+
+    assert_eq!(Some((1, 1)), screen.area_position(4, 3));
+    assert_eq!(Some((4, 1)), screen.area_position(7, 3));
+    assert_eq!(None, screen.area_position(3, 3));
+    assert_eq!(None, screen.area_position(8, 3));
+    assert_eq!(None, screen.area_position(4, 4));
+}
@@ -0,0 +1,181 @@
+use std::{io, mem, panic, process, thread};
+use std::io::{stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+use signal_hook::iterator::Signals;
+
+use crate::common::terminal;
+
+/// The same as `termion::input::MouseTerminal`, which can't be written again after a suspend.
+const MOUSE_ON: &str = "\x1b[?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h";
+const MOUSE_OFF: &str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l";
+
+/// The terminal attributes from before the session, to put them back.
+static ORIGINAL: OnceLock<libc::termios> = OnceLock::new();
+/// Whether the terminal is set up for the games, so it is put back only once.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Held while writing to the terminal, so the signals never put it back in the middle of a frame.
+static OUTPUT: Mutex<()> = Mutex::new(());
+
+/// The terminal set up for the games: raw, on the alternate screen, with the mouse and without the cursor. It is
+/// put back as it was however the games end: when the session is dropped, on a panic, or on SIGINT or SIGTERM.
+/// Ctrl-Z suspends the games with the terminal put back, and `fg` sets it up again and redraws the game.
+/// What is written is kept until the flush, which writes it all at once.
+pub struct Session {
+    buffer: Vec<u8>,
+}
+
+impl Session {
+    /// Fails when we are not in a terminal.
+    pub fn start() -> io::Result<Session> {
+        let original = attributes()?;
+        let _ = ORIGINAL.set(original);
+
+        enter()?;
+
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            leave();
+            previous(info);
+        }));
+
+        let signals = Signals::new([libc::SIGINT, libc::SIGTERM, libc::SIGTSTP, libc::SIGCONT])?;
+
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                match signal {
+                    libc::SIGTSTP => {
+                        leave();
+                        // we handle SIGTSTP, so it wouldn't stop us
+                        unsafe { libc::raise(libc::SIGSTOP) };
+                    }
+                    libc::SIGCONT => if !ACTIVE.load(Ordering::SeqCst) && enter().is_ok() {
+                        terminal::redraw();
+                    },
+                    _ => {
+                        leave();
+                        process::exit(128 + signal);
+                    }
+                }
+            }
+        });
+
+        Ok(Session { buffer: Vec::new() })
+    }
+}
+
+impl Write for Session {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _output = lock_output();
+
+        // while suspended the frame is dropped, the game is drawn again on SIGCONT
+        let result = if ACTIVE.load(Ordering::SeqCst) { write_out(&self.buffer) } else { Ok(()) };
+        self.buffer.clear();
+
+        result
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.flush();
+        leave();
+    }
+}
+
+fn lock_output() -> MutexGuard<'static, ()> {
+    OUTPUT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write_out(bytes: &[u8]) -> io::Result<()> {
+    let mut stdout = stdout();
+    stdout.write_all(bytes)?;
+    stdout.flush()
+}
+
+fn enter() -> io::Result<()> {
+    let original = *ORIGINAL.get().ok_or_else(|| io::Error::other("no session"))?;
+
+    let _output = lock_output();
+
+    set_attributes(&raw(original))?;
+
+    let mut set_up_sequence = Vec::new();
+    set_up(&mut set_up_sequence)?;
+    write_out(&set_up_sequence)?;
+
+    ACTIVE.store(true, Ordering::SeqCst);
+
+    Ok(())
+}
+
+/// Puts the terminal back as it was; errors are ignored since there is nothing more we could do.
+fn leave() {
+    let _output = lock_output();
+
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    let mut put_back_sequence = Vec::new();
+    let _ = put_back(&mut put_back_sequence);
+    let _ = write_out(&put_back_sequence);
+
+    if let Some(original) = ORIGINAL.get() {
+        let _ = set_attributes(original);
+    }
+}
+
+/// Switches to the alternate screen, with the mouse and without the cursor; raw mode is up to the caller.
+pub fn set_up<W: Write>(out: &mut W) -> io::Result<()> {
+    write!(out, "{}{}{}", termion::screen::ToAlternateScreen, MOUSE_ON, termion::cursor::Hide)
+}
+
+/// Undoes `set_up`, and the colors.
+pub fn put_back<W: Write>(out: &mut W) -> io::Result<()> {
+    write!(out, "{}{}{}{}", termion::style::Reset, termion::cursor::Show, MOUSE_OFF, termion::screen::ToMainScreen)
+}
+
+/// Raw mode, as `termion::raw` sets it, but Ctrl-C and Ctrl-Z still send SIGINT and SIGTSTP.
+fn raw(original: libc::termios) -> libc::termios {
+    let mut raw = original;
+
+    unsafe { libc::cfmakeraw(&mut raw) };
+    raw.c_lflag |= libc::ISIG;
+
+    raw
+}
+
+fn attributes() -> io::Result<libc::termios> {
+    let mut attributes = unsafe { mem::zeroed() };
+
+    if unsafe { libc::tcgetattr(libc::STDOUT_FILENO, &mut attributes) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(attributes)
+}
+
+fn set_attributes(attributes: &libc::termios) -> io::Result<()> {
+    if unsafe { libc::tcsetattr(libc::STDOUT_FILENO, libc::TCSANOW, attributes) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn raw_mode_should_keep_the_signals_of_ctrl_c_and_ctrl_z() {
+    let mut cooked: libc::termios = unsafe { mem::zeroed() };
+    cooked.c_lflag = libc::ICANON | libc::ECHO | libc::ISIG;
+
+    let raw = raw(cooked);
+
+    assert_eq!(libc::ISIG, raw.c_lflag & (libc::ICANON | libc::ECHO | libc::ISIG));
+}
@@ -0,0 +1,307 @@
+use std::{fs, io};
+use std::collections::BTreeMap;
+use std::fs::create_dir_all;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate};
+
+use crate::common::difficulty::Difficulty;
+use crate::common::persistence::{data_file, lock, write_atomically};
+use crate::common::result::{Counter, GameResult, Outcome};
+
+/// How many of the last scores are kept for the median.
+const RECENT_SCORES: usize = 1000;
+
+/// What a player did in all the games of one day.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Day {
+    pub games: u32,
+    /// In seconds.
+    pub play_time: u64,
+}
+
+/// Sums up all the games played to the end.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LifetimeStats {
+    #[serde(skip)]
+    path: String,
+    #[serde(skip)]
+    difficulty: Difficulty,
+    games: u32,
+    /// In seconds.
+    play_time: u64,
+    /// Games which had a score.
+    scored: u32,
+    score_sum: u64,
+    /// The last `RECENT_SCORES`, oldest first.
+    scores: Vec<u32>,
+    /// Games cleared in a row, up to the last one.
+    clear_streak: u32,
+    best_clear_streak: u32,
+    days: BTreeMap<NaiveDate, Day>,
+    totals: BTreeMap<String, u64>,
+    longest: BTreeMap<String, u32>,
+}
+
+impl LifetimeStats {
+    fn new(path: &str, difficulty: Difficulty) -> LifetimeStats {
+        LifetimeStats { path: path.to_string(), difficulty, ..LifetimeStats::default() }
+    }
+
+    /// A corrupt file is moved aside.
+    pub fn read(path: &str, difficulty: Difficulty) -> io::Result<LifetimeStats> {
+        let file = LifetimeStats::file(path, difficulty)?;
+
+        if !file.exists() {
+            return Ok(LifetimeStats::new(path, difficulty));
+        }
+
+        let _lock = lock(&file)?;
+
+        LifetimeStats::read_file(path, difficulty, &file)
+    }
+
+    /// Adds a game to the stats on disk, which another terminal may have changed.
+    pub fn record(&mut self, result: &GameResult, today: NaiveDate) -> io::Result<()> {
+        let file = LifetimeStats::file(&self.path, self.difficulty)?;
+
+        create_dir_all(file.parent().unwrap())?;
+
+        let _lock = lock(&file)?;
+
+        if file.exists() {
+            *self = LifetimeStats::read_file(&self.path, self.difficulty, &file)?;
+        }
+
+        self.add(result, today);
+
+        let serialized = serde_json::to_string_pretty(self).map_err(|e| Error::other(e.to_string()))?;
+
+        write_atomically(&file, &serialized)
+    }
+
+    fn read_file(path: &str, difficulty: Difficulty, file: &Path) -> io::Result<LifetimeStats> {
+        match serde_json::from_str(&fs::read_to_string(file)?) {
+            Ok(stats) => Ok(LifetimeStats { path: path.to_string(), difficulty, ..stats }),
+            Err(_) => {
+                fs::rename(file, file.with_extension(format!("{}.corrupt", Local::now().format("%Y%m%d-%H%M%S"))))?;
+
+                Ok(LifetimeStats::new(path, difficulty))
+            }
+        }
+    }
+
+    fn keep_recent_scores(&mut self) {
+        let excess = self.scores.len().saturating_sub(RECENT_SCORES);
+        self.scores.drain(..excess);
+    }
+
+    fn add(&mut self, result: &GameResult, today: NaiveDate) {
+        let seconds = result.duration.as_secs();
+
+        self.games += 1;
+        self.play_time += seconds;
+
+        if let Some(score) = result.score {
+            self.scored += 1;
+            self.score_sum += u64::from(score);
+            self.scores.push(score);
+            self.keep_recent_scores();
+        }
+
+        if result.outcome == Outcome::Cleared {
+            self.clear_streak += 1;
+            self.best_clear_streak = self.best_clear_streak.max(self.clear_streak);
+        } else {
+            self.clear_streak = 0;
+        }
+
+        let day = self.days.entry(today).or_default();
+        day.games += 1;
+        day.play_time += seconds;
+
+        for counter in &result.counters {
+            match *counter {
+                Counter::Total(name, value) => *self.totals.entry(name.to_string()).or_default() += u64::from(value),
+                Counter::Longest(name, value) => {
+                    let longest = self.longest.entry(name.to_string()).or_default();
+                    *longest = (*longest).max(value);
+                }
+            }
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.games
+    }
+
+    pub fn play_time(&self) -> Duration {
+        Duration::from_secs(self.play_time)
+    }
+
+    pub fn average(&self) -> Option<u32> {
+        if self.scored == 0 {
+            return None;
+        }
+
+        Some((self.score_sum / u64::from(self.scored)) as u32)
+    }
+
+    /// Of the last `RECENT_SCORES`, the lower middle one.
+    pub fn median(&self) -> Option<u32> {
+        let mut scores = self.scores.clone();
+        scores.sort_unstable();
+
+        scores.get(scores.len().saturating_sub(1) / 2).copied()
+    }
+
+    pub fn best_clear_streak(&self) -> u32 {
+        self.best_clear_streak
+    }
+
+    /// The most days played in a row.
+    pub fn best_day_streak(&self) -> u32 {
+        let mut best = 0;
+        let mut streak = 0;
+        let mut previous: Option<NaiveDate> = None;
+
+        for &day in self.days.keys() {
+            streak = if previous.map(|previous| previous.succ()) == Some(day) { streak + 1 } else { 1 };
+            best = best.max(streak);
+            previous = Some(day);
+        }
+
+        best
+    }
+
+    /// The last `days` days up to `today`, oldest first.
+    pub fn history(&self, today: NaiveDate, days: u32) -> Vec<(NaiveDate, Day)> {
+        let mut history = Vec::new();
+        let mut day = today;
+
+        for _ in 0..days {
+            history.push((day, self.days.get(&day).copied().unwrap_or_default()));
+            day = day.pred();
+        }
+
+        history.reverse();
+        history
+    }
+
+    pub fn totals(&self) -> &BTreeMap<String, u64> {
+        &self.totals
+    }
+
+    pub fn longest(&self) -> &BTreeMap<String, u32> {
+        &self.longest
+    }
+
+    fn file(path: &str, difficulty: Difficulty) -> io::Result<PathBuf> {
+        data_file(path, &format!("stats{}.json", difficulty.suffix()))
+    }
+}
+
+#[cfg(test)]
+use crate::common::headless::{Headless, Script};
+
+#[cfg(test)]
+fn result(outcome: Outcome, score: u32, counters: Vec<Counter>) -> GameResult {
+    GameResult::new(outcome, Some(score), Duration::from_secs(60), "10x20".to_string(), Vec::new()).counting(counters)
+}
+
+#[cfg(test)]
+fn day(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd(2020, 5, day)
+}
+
+#[test]
+fn given_no_games_then_there_should_be_no_average_nor_median() {
+    let stats = LifetimeStats::default();
+
+    assert_eq!(None, stats.average());
+    assert_eq!(None, stats.median());
+}
+
+#[test]
+fn average_and_median_should_be_of_all_the_scores() {
+    let mut stats = LifetimeStats::default();
+
+    for &score in &[10, 400, 30, 20] {
+        stats.add(&result(Outcome::Lost, score, Vec::new()), day(1));
+    }
+
+    assert_eq!(Some(115), stats.average());
+    assert_eq!(Some(20), stats.median());
+    assert_eq!(4, stats.games());
+    assert_eq!(Duration::from_secs(240), stats.play_time());
+}
+
+#[test]
+fn totals_should_add_up_and_longest_keep_the_best() {
+    let mut stats = LifetimeStats::default();
+
+    stats.add(&result(Outcome::Lost, 0, vec!(Counter::Total("lines", 3), Counter::Longest("ticks", 50))), day(1));
+    stats.add(&result(Outcome::Lost, 0, vec!(Counter::Total("lines", 4), Counter::Longest("ticks", 20))), day(1));
+
+    assert_eq!(Some(&7), stats.totals().get("lines"));
+    assert_eq!(Some(&50), stats.longest().get("ticks"));
+}
+
+#[test]
+fn streaks_should_be_of_consecutive_clears_and_days() {
+    let mut stats = LifetimeStats::default();
+
+    for &(outcome, date) in &[(Outcome::Cleared, day(1)), (Outcome::Cleared, day(2)), (Outcome::Lost, day(3)),
+                              (Outcome::Cleared, day(5)), (Outcome::Cleared, day(5))] {
+        stats.add(&result(outcome, 0, Vec::new()), date);
+    }
+
+    assert_eq!(2, stats.best_clear_streak());
+    assert_eq!(3, stats.best_day_streak());
+}
+
+#[test]
+fn history_should_include_the_days_without_games() {
+    let mut stats = LifetimeStats::default();
+
+    stats.add(&result(Outcome::Lost, 0, Vec::new()), day(2));
+    stats.add(&result(Outcome::Lost, 0, Vec::new()), day(2));
+
+    let games: Vec<(NaiveDate, u32)> = stats.history(day(3), 3).iter().map(|(date, day)| (*date, day.games)).collect();
+    assert_eq!(vec!((day(1), 0), (day(2), 2), (day(3), 0)), games);
+}
+
+#[test]
+fn recorded_games_should_be_read_back() {
+    let headless = Headless::new(80, 24);
+
+    let (stats, _) = headless.run(Script::new(), |_, _| {
+        LifetimeStats::read("test", Difficulty::Normal)?.record(&result(Outcome::Lost, 100, Vec::new()), day(1))?;
+        LifetimeStats::read("test", Difficulty::Normal)?.record(&result(Outcome::Lost, 300, Vec::new()), day(2))?;
+        LifetimeStats::read("test", Difficulty::Hard)?.record(&result(Outcome::Lost, 5, Vec::new()), day(2))?;
+
+        LifetimeStats::read("test", Difficulty::Normal)
+    });
+
+    let stats = stats.unwrap();
+    assert_eq!(2, stats.games());
+    assert_eq!(Some(200), stats.average());
+    assert_eq!(2, stats.best_day_streak());
+}
+
+#[test]
+fn only_the_last_scores_should_be_kept_but_the_average_should_be_of_all() {
+    let mut stats = LifetimeStats::default();
+
+    stats.add(&result(Outcome::Lost, 1_000_000, Vec::new()), day(1));
+    for _ in 0..RECENT_SCORES {
+        stats.add(&result(Outcome::Lost, 1000, Vec::new()), day(1));
+    }
+
+    assert_eq!(RECENT_SCORES, stats.scores.len());
+    assert_eq!(Some(1000), stats.median());
+    assert_eq!(Some(1998), stats.average());
+}
@@ -0,0 +1,148 @@
+/// Ctrl-C, which clients send as it is in character mode.
+const ETX: u8 = 3;
+
+/// Telnet commands, from RFC 854.
+const SE: u8 = 240;
+const IP: u8 = 244;
+const SB: u8 = 250;
+const WILL: u8 = 251;
+const DO: u8 = 253;
+const IAC: u8 = 255;
+
+/// Telnet options.
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+/// Negotiate About Window Size, RFC 1073.
+const NAWS: u8 = 31;
+
+/// Sent to a client as it connects: we echo, there are no go aheads either way, which puts clients in character
+/// mode, and the client should tell the size of its window, and when it changes.
+pub const NEGOTIATION: [u8; 12] = [IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD, IAC, DO, SUPPRESS_GO_AHEAD, IAC, DO, NAWS];
+
+/// What a client tells, besides the keys it sends.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// The width and height of its window.
+    Size(u16, u16),
+    /// The player wants to stop, with Ctrl-C.
+    Interrupt,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    Data,
+    /// After a carriage return, which the client may follow with a line feed or a NUL.
+    Return,
+    Command,
+    /// After WILL, WONT, DO or DONT, waiting for its option.
+    Option,
+    Subnegotiation,
+    /// After IAC in a subnegotiation.
+    SubnegotiationCommand,
+}
+
+/// Separates the keys a client sends from the telnet commands among them. The bytes may be split anywhere.
+pub struct Decoder {
+    state: State,
+    subnegotiation: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder { state: State::Data, subnegotiation: Vec::new() }
+    }
+
+    /// The keys, and the events among them. The answers of the client to our negotiation are ignored: we don't
+    /// offer anything else if it refuses.
+    pub fn decode(&mut self, bytes: &[u8]) -> (Vec<u8>, Vec<Event>) {
+        let mut keys = Vec::new();
+        let mut events = Vec::new();
+
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (State::Return, 0) | (State::Return, b'\n') => State::Data,
+                (State::Data, IAC) | (State::Return, IAC) => State::Command,
+                (State::Data, ETX) | (State::Return, ETX) => {
+                    events.push(Event::Interrupt);
+                    State::Data
+                }
+                (State::Data, b'\r') | (State::Return, b'\r') => {
+                    keys.push(byte);
+                    State::Return
+                }
+                (State::Data, _) | (State::Return, _) => {
+                    keys.push(byte);
+                    State::Data
+                }
+                (State::Command, IAC) => {
+                    keys.push(IAC);
+                    State::Data
+                }
+                (State::Command, SB) => {
+                    self.subnegotiation.clear();
+                    State::Subnegotiation
+                }
+                (State::Command, IP) => {
+                    events.push(Event::Interrupt);
+                    State::Data
+                }
+                (State::Command, WILL..=IAC) => State::Option,
+                (State::Command, _) | (State::Option, _) => State::Data,
+                (State::Subnegotiation, IAC) => State::SubnegotiationCommand,
+                (State::Subnegotiation, _) => {
+                    self.subnegotiation.push(byte);
+                    State::Subnegotiation
+                }
+                (State::SubnegotiationCommand, SE) => {
+                    events.extend(self.subnegotiated());
+                    State::Data
+                }
+                (State::SubnegotiationCommand, _) => {
+                    self.subnegotiation.push(byte);
+                    State::Subnegotiation
+                }
+            };
+        }
+
+        (keys, events)
+    }
+
+    fn subnegotiated(&self) -> Option<Event> {
+        match self.subnegotiation.as_slice() {
+            [NAWS, width_high, width_low, height_high, height_low] => Some(Event::Size(
+                u16::from_be_bytes([*width_high, *width_low]), u16::from_be_bytes([*height_high, *height_low]))),
+            _ => None
+        }
+    }
+}
+
+#[test]
+fn keys_should_be_separated_from_commands() {
+    let mut decoder = Decoder::new();
+
+    let (keys, events) = decoder.decode(&[b'a', IAC, WILL, NAWS, IAC, IAC, b'\r', 0, b'b', b'\r', b'\n']);
+
+    assert_eq!(vec!(b'a', IAC, b'\r', b'b', b'\r'), keys);
+    assert!(events.is_empty());
+}
+
+#[test]
+fn the_window_size_should_be_told_even_if_split() {
+    let mut decoder = Decoder::new();
+
+    let (keys, first) = decoder.decode(&[IAC, SB, NAWS, 0, 120]);
+    let (_, second) = decoder.decode(&[0, 40, IAC, SE, IAC, IP]);
+
+    assert!(keys.is_empty() && first.is_empty());
+    assert_eq!(vec!(Event::Size(120, 40), Event::Interrupt), second);
+}
+
+#[test]
+fn ctrl_c_should_interrupt_in_character_mode_as_in_line_mode() {
+    let mut decoder = Decoder::new();
+
+    let (keys, events) = decoder.decode(&[b'a', ETX, IAC, IP]);
+
+    assert_eq!(vec!(b'a'), keys);
+    assert_eq!(vec!(Event::Interrupt, Event::Interrupt), events);
+}
@@ -0,0 +1,98 @@
+use std::cell::RefCell;
+#[cfg(test)]
+use std::cell::Cell;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Used when the size can't be read, e.g. when we are not writing to a terminal.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+static RESIZED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+#[cfg(test)]
+thread_local! {
+    /// Replaces the size of the real terminal in headless tests.
+    pub static TEST_SIZE: Cell<Option<(u16, u16)>> = const { Cell::new(None) };
+    /// Whether the test size changed, as SIGWINCH tells for the real terminal.
+    static TEST_RESIZED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Resizes the terminal of a headless test.
+#[cfg(test)]
+pub fn resize_test(width: u16, height: u16) {
+    TEST_SIZE.with(|size| size.set(Some((width, height))));
+    TEST_RESIZED.with(|resized| resized.set(true));
+}
+
+thread_local! {
+    /// The terminal of the player of this thread, when it isn't ours.
+    static REMOTE: RefCell<Option<Remote>> = const { RefCell::new(None) };
+}
+
+/// A terminal we don't own, e.g. of a telnet client, which tells its size itself. Clones share the size.
+#[derive(Clone, Default)]
+pub struct Remote {
+    size: Arc<Mutex<Option<(u16, u16)>>>,
+    resized: Arc<AtomicBool>,
+}
+
+impl Remote {
+    pub fn resize(&self, width: u16, height: u16) {
+        *self.size.lock().unwrap() = Some((width, height));
+        self.resized.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Makes `size` and `resized` those of the remote terminal, in this thread.
+pub fn set_remote(remote: Remote) {
+    REMOTE.with(|current| *current.borrow_mut() = Some(remote));
+}
+
+/// Whether the player of this thread is on a remote terminal. They share our files with other players, so they
+/// don't save games nor settings.
+pub fn is_remote() -> bool {
+    REMOTE.with(|remote| remote.borrow().is_some())
+}
+
+/// Width and height of the terminal.
+pub fn size() -> (u16, u16) {
+    #[cfg(test)]
+    if let Some(size) = TEST_SIZE.with(Cell::get) {
+        return size;
+    }
+
+    if let Some(size) = REMOTE.with(|remote| remote.borrow().as_ref().map(|remote| *remote.size.lock().unwrap())) {
+        // until the client tells its size
+        return size.unwrap_or(DEFAULT_SIZE);
+    }
+
+    termion::terminal_size().unwrap_or(DEFAULT_SIZE)
+}
+
+/// Whether the terminal has been resized (SIGWINCH) since the last call, or has to be drawn again anyway.
+pub fn resized() -> bool {
+    #[cfg(test)]
+    if TEST_SIZE.with(Cell::get).is_some() {
+        return TEST_RESIZED.with(|resized| resized.replace(false));
+    }
+
+    if let Some(resized) = REMOTE.with(|remote| remote.borrow().as_ref().map(|remote| remote.resized.swap(false, Ordering::Relaxed))) {
+        return resized;
+    }
+
+    resized_flag().swap(false, Ordering::Relaxed)
+}
+
+/// Makes the next call to `resized` true, so that what is on the terminal is drawn again, e.g. after a suspend.
+pub fn redraw() {
+    resized_flag().store(true, Ordering::Relaxed)
+}
+
+fn resized_flag() -> &'static Arc<AtomicBool> {
+    RESIZED.get_or_init(|| {
+        let flag = Arc::new(AtomicBool::new(false));
+        // without the handler we never know about resizes, but the games still work
+        let _ = signal_hook::flag::register(signal_hook::SIGWINCH, flag.clone());
+        flag
+    })
+}
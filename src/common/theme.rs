@@ -0,0 +1,368 @@
+use std::cell::Cell;
+use std::{env, fmt, fs, io};
+use std::path::PathBuf;
+
+use crate::common::consolecolor::Color;
+use crate::common::glyphs;
+use crate::common::persistence::{config_file, write_atomically};
+
+const CONFIG_FILE: &str = "theme";
+
+/// The 16 ANSI colors, as xterm shows them.
+const ANSI: [(u8, u8, u8); 16] = [(0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), (0, 0, 238), (205, 0, 205),
+    (0, 205, 205), (229, 229, 229), (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0), (92, 92, 255),
+    (255, 0, 255), (0, 255, 255), (255, 255, 255)];
+/// The levels of the 6x6x6 cube of the 256 colors.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Shown as the nearest color the terminal has.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Paint {
+    Default,
+    /// One of the 16 ANSI colors, the bright ones from 8.
+    Ansi(u8),
+    /// One of the 256 colors.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Paint {
+    /// None when the terminal shows no color.
+    fn at(self, depth: Depth) -> Option<Paint> {
+        let paint = match (self, depth) {
+            (_, Depth::Monochrome) => return None,
+            (Paint::Indexed(index), Depth::Ansi) => Paint::Ansi(nearest_ansi(rgb(index))),
+            (Paint::Rgb(r, g, b), Depth::Ansi) => Paint::Ansi(nearest_ansi((r, g, b))),
+            (Paint::Rgb(r, g, b), Depth::Indexed) => Paint::Indexed(nearest_indexed((r, g, b))),
+            (paint, _) => paint
+        };
+
+        Some(paint)
+    }
+
+    /// A foreground when `ground` is 3, a background when 4.
+    fn write(self, f: &mut fmt::Formatter, ground: u8, depth: Depth) -> fmt::Result {
+        match self.at(depth) {
+            None => Ok(()),
+            Some(Paint::Default) => write!(f, "\x1b[{}9m", ground),
+            Some(Paint::Ansi(n)) if n < 8 => write!(f, "\x1b[{}{}m", ground, n),
+            Some(Paint::Ansi(n)) => write!(f, "\x1b[{}m", ground as u16 * 10 + 60 + (n as u16 - 8)),
+            Some(Paint::Indexed(n)) => write!(f, "\x1b[{}8;5;{}m", ground, n),
+            Some(Paint::Rgb(r, g, b)) => write!(f, "\x1b[{}8;2;{};{};{}m", ground, r, g, b)
+        }
+    }
+}
+
+fn rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI[index as usize],
+        16..=231 => {
+            let cube = index - 16;
+            (CUBE[(cube / 36) as usize], CUBE[(cube / 6 % 6) as usize], CUBE[(cube % 6) as usize])
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let component = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    component(a.0, b.0) + component(a.1, b.1) + component(a.2, b.2)
+}
+
+fn nearest_ansi(color: (u8, u8, u8)) -> u8 {
+    (0..16).min_by_key(|&index| distance(ANSI[index as usize], color)).unwrap()
+}
+
+/// Leaves out the 16 ANSI colors, which terminals change.
+fn nearest_indexed(color: (u8, u8, u8)) -> u8 {
+    let level = |component: u8| (0..6).min_by_key(|&level| (CUBE[level] as i32 - component as i32).abs()).unwrap() as u8;
+    let cube = 16 + 36 * level(color.0) + 6 * level(color.1) + level(color.2);
+
+    let average = (color.0 as u32 + color.1 as u32 + color.2 as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+    if distance(rgb(gray), color) < distance(rgb(cube), color) { gray } else { cube }
+}
+
+/// How many colors the terminal shows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Depth {
+    Monochrome,
+    Ansi,
+    Indexed,
+    TrueColor,
+}
+
+impl Depth {
+    /// From NO_COLOR, COLORTERM and TERM.
+    pub fn detect() -> Depth {
+        let var = |name| env::var(name).unwrap_or_default();
+        Depth::of(&var("NO_COLOR"), &var("COLORTERM"), &var("TERM"))
+    }
+
+    fn of(no_color: &str, colorterm: &str, term: &str) -> Depth {
+        if !no_color.is_empty() {
+            Depth::Monochrome
+        } else if colorterm == "truecolor" || colorterm == "24bit" {
+            Depth::TrueColor
+        } else if term.contains("256color") {
+            Depth::Indexed
+        } else {
+            Depth::Ansi
+        }
+    }
+}
+
+/// What the menus and boxes paint, outside of the games.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    /// Of the screens and boxes.
+    Title,
+    /// Also a text being typed.
+    Selected,
+    /// Where the text is typed.
+    Cursor,
+    /// Around the boxes and the games.
+    Border,
+    /// Of a bar chart.
+    Bar,
+    /// Keys to press in a text.
+    Emphasis,
+    /// A new high score in a table.
+    Highlight,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let style = current();
+
+        if style.colorless() {
+            // what is left to tell them apart
+            return match self {
+                Role::Title | Role::Selected => write!(f, "{}", termion::style::Invert),
+                Role::Cursor | Role::Highlight => write!(f, "{}", termion::style::Underline),
+                Role::Emphasis => write!(f, "{}", termion::style::Bold),
+                Role::Border | Role::Bar => Ok(())
+            };
+        }
+
+        let (fg, bg) = style.theme.roles[*self as usize];
+
+        fg.write(f, 3, style.depth)?;
+        bg.write(f, 4, style.depth)
+    }
+}
+
+/// A palette, for the colors of the games and the roles.
+#[derive(Debug, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    /// In the order of `Color`.
+    colors: [Paint; 8],
+    /// Foreground and background, in the order of `Role`.
+    roles: [(Paint, Paint); 7],
+    monochrome: bool,
+}
+
+pub const DARK: Theme = Theme {
+    name: "Dark",
+    colors: [Paint::Ansi(0), Paint::Ansi(1), Paint::Ansi(2), Paint::Ansi(3), Paint::Ansi(4), Paint::Ansi(5),
+        Paint::Ansi(6), Paint::Ansi(7)],
+    roles: [(Paint::Ansi(15), Paint::Ansi(2)), (Paint::Ansi(15), Paint::Ansi(6)), (Paint::Ansi(0), Paint::Ansi(15)),
+        (Paint::Ansi(0), Paint::Ansi(7)), (Paint::Default, Paint::Ansi(2)), (Paint::Ansi(15), Paint::Default),
+        (Paint::Ansi(2), Paint::Default)],
+    monochrome: false,
+};
+
+/// For terminals with a light background.
+pub const LIGHT: Theme = Theme {
+    name: "Light",
+    colors: [Paint::Rgb(40, 40, 40), Paint::Rgb(200, 40, 40), Paint::Rgb(30, 140, 60), Paint::Rgb(200, 150, 0),
+        Paint::Rgb(40, 80, 200), Paint::Rgb(160, 50, 170), Paint::Rgb(0, 140, 150), Paint::Rgb(120, 120, 120)],
+    roles: [(Paint::Rgb(255, 255, 255), Paint::Rgb(30, 110, 50)), (Paint::Rgb(255, 255, 255), Paint::Rgb(40, 80, 200)),
+        (Paint::Rgb(255, 255, 255), Paint::Rgb(40, 40, 40)), (Paint::Rgb(255, 255, 255), Paint::Rgb(90, 90, 90)),
+        (Paint::Default, Paint::Rgb(30, 140, 60)), (Paint::Rgb(0, 0, 0), Paint::Default),
+        (Paint::Rgb(30, 110, 50), Paint::Default)],
+    monochrome: false,
+};
+
+pub const HIGH_CONTRAST: Theme = Theme {
+    name: "High contrast",
+    colors: [Paint::Ansi(8), Paint::Ansi(9), Paint::Ansi(10), Paint::Ansi(11), Paint::Ansi(12), Paint::Ansi(13),
+        Paint::Ansi(14), Paint::Ansi(15)],
+    roles: [(Paint::Ansi(0), Paint::Ansi(11)), (Paint::Ansi(0), Paint::Ansi(15)), (Paint::Ansi(15), Paint::Ansi(0)),
+        (Paint::Ansi(0), Paint::Ansi(15)), (Paint::Default, Paint::Ansi(11)), (Paint::Ansi(11), Paint::Default),
+        (Paint::Ansi(10), Paint::Default)],
+    monochrome: false,
+};
+
+pub const MONOCHROME: Theme = Theme {
+    name: "Monochrome",
+    colors: [Paint::Default; 8],
+    roles: [(Paint::Default, Paint::Default); 7],
+    monochrome: true,
+};
+
+pub static THEMES: [&Theme; 4] = [&DARK, &LIGHT, &HIGH_CONTRAST, &MONOCHROME];
+
+/// The theme in use, and how many colors the terminal shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+    pub theme: &'static Theme,
+    pub depth: Depth,
+}
+
+impl Style {
+    /// Whether blanks on a background are drawn with glyphs.
+    pub fn colorless(&self) -> bool {
+        self.theme.monochrome || self.depth == Depth::Monochrome
+    }
+
+    fn paint(&self, color: Color) -> Paint {
+        match color {
+            Color::DefaultColor => Paint::Default,
+            Color::Border => self.theme.roles[Role::Border as usize].1,
+            color => self.theme.colors[color as usize]
+        }
+    }
+
+    /// A foreground when `ground` is 3, a background when 4.
+    pub fn write(&self, f: &mut fmt::Formatter, color: Color, ground: u8) -> fmt::Result {
+        self.paint(color).write(f, ground, self.depth)
+    }
+
+    /// Without colors, a blank has a glyph for each background.
+    pub fn glyph(&self, c: char, bg: Color) -> char {
+        if c != ' ' || !self.colorless() {
+            return c;
+        }
+
+        match bg {
+            Color::DefaultColor => ' ',
+            Color::Black => '+',
+            Color::Red => '@',
+            Color::Green => '%',
+            Color::Yellow => '$',
+            Color::Blue => '&',
+            Color::Magenta => '=',
+            Color::Cyan => '~',
+            Color::White | Color::Border => full()
+        }
+    }
+
+    /// For a role painted only with a background, e.g. a border.
+    pub fn blank(&self, role: Role) -> char {
+        match role {
+            Role::Border if self.colorless() => full(),
+            Role::Bar if self.colorless() => '#',
+            _ => ' '
+        }
+    }
+}
+
+/// A full block, unless the glyphs are only ASCII.
+fn full() -> char {
+    if glyphs::current().unicode() { '█' } else { '#' }
+}
+
+thread_local! {
+    static CURRENT: Cell<Option<Style>> = const { Cell::new(None) };
+}
+
+/// The Dark theme until another is set.
+pub fn current() -> Style {
+    CURRENT.with(|current| current.get()).unwrap_or_else(|| {
+        // the tests don't depend on the terminal they run in
+        let depth = if cfg!(test) { Depth::Ansi } else { Depth::detect() };
+        let style = Style { theme: &DARK, depth };
+        CURRENT.with(|current| current.set(Some(style)));
+        style
+    })
+}
+
+pub fn set(theme: &'static Theme) {
+    let depth = current().depth;
+    CURRENT.with(|current| current.set(Some(Style { theme, depth })));
+}
+
+/// Draws with the colors of the depth, e.g. those of a remote terminal rather than of ours.
+pub fn set_depth(depth: Depth) {
+    let theme = current().theme;
+    CURRENT.with(|current| current.set(Some(Style { theme, depth })));
+}
+
+/// The theme of the name, ignoring case.
+pub fn find(name: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|theme| theme.name.eq_ignore_ascii_case(name.trim())).cloned()
+}
+
+/// Sets the theme chosen in the settings, if any.
+pub fn load() -> io::Result<()> {
+    let file = file()?;
+
+    if file.exists() {
+        if let Some(theme) = find(&fs::read_to_string(file)?) {
+            set(theme);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn save(theme: &'static Theme) -> io::Result<()> {
+    set(theme);
+
+    let file = file()?;
+    fs::create_dir_all(file.parent().unwrap())?;
+
+    write_atomically(&file, theme.name)
+}
+
+fn file() -> io::Result<PathBuf> {
+    config_file(CONFIG_FILE)
+}
+
+#[cfg(test)]
+struct Painted(Paint, Depth);
+
+#[cfg(test)]
+impl fmt::Display for Painted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.write(f, 3, self.1)
+    }
+}
+
+#[test]
+fn no_color_should_win_over_the_terminal() {
+    assert_eq!(Depth::Monochrome, Depth::of("1", "truecolor", "xterm-256color"));
+    assert_eq!(Depth::TrueColor, Depth::of("", "truecolor", "xterm-256color"));
+    assert_eq!(Depth::Indexed, Depth::of("", "", "xterm-256color"));
+    assert_eq!(Depth::Ansi, Depth::of("", "", "xterm"));
+}
+
+#[test]
+fn colors_should_be_the_nearest_the_terminal_shows() {
+    let orange = Paint::Rgb(255, 135, 0);
+
+    assert_eq!("\x1b[38;2;255;135;0m", Painted(orange, Depth::TrueColor).to_string());
+    assert_eq!("\x1b[38;5;208m", Painted(orange, Depth::Indexed).to_string());
+    assert_eq!("\x1b[33m", Painted(orange, Depth::Ansi).to_string());
+    assert_eq!("\x1b[38;5;244m", Painted(Paint::Rgb(128, 128, 128), Depth::Indexed).to_string());
+    assert_eq!("\x1b[31m", Painted(Paint::Indexed(160), Depth::Ansi).to_string());
+    assert_eq!("", Painted(orange, Depth::Monochrome).to_string());
+}
+
+#[test]
+fn without_colors_blanks_should_be_drawn_with_a_glyph_for_each_background() {
+    let style = Style { theme: &MONOCHROME, depth: Depth::TrueColor };
+
+    assert_eq!('@', style.glyph(' ', Color::Red));
+    assert_eq!('&', style.glyph(' ', Color::Blue));
+    assert_eq!('x', style.glyph('x', Color::Red));
+    assert_eq!(' ', style.glyph(' ', Color::DefaultColor));
+    assert_eq!(' ', Style { theme: &DARK, depth: Depth::Ansi }.glyph(' ', Color::Red));
+    assert_eq!('#', Style { theme: &DARK, depth: Depth::Monochrome }.blank(Role::Border));
+}
@@ -5,150 +5,454 @@ extern crate serde_derive;
 extern crate serde_json;
 extern crate termion;
 
-use std::{io, thread};
-use std::io::{Read, stdout, Write};
+use std::{env, io, process, thread};
+use std::io::{Read, Write};
+use std::path::Path;
 use std::time::Duration;
 
 use chrono::{DateTime, Local};
-use termion::{async_stdin, color};
+use termion::async_stdin;
 use termion::event::Key;
 use termion::event::Key::Char;
 use termion::input::TermRead;
-use termion::raw::IntoRawMode;
 
 use crate::arkanoid::arkanoidmain::ArkanoidMain;
-use crate::common::ioutils::wait_for_key_async;
-use crate::common::persistence::HighScores;
+use crate::cli::Command;
+use crate::common::difficulty::{CUSTOM_SPEEDS, Difficulty};
+use crate::common::glyphs;
+use crate::common::help::Help;
+use crate::common::ioutils::{wait_for_any_key_async, wait_for_key_async};
+use crate::common::menu::{Choice, Setting, SettingValue};
+use crate::common::options::GameOptions;
+use crate::common::persistence;
+use crate::common::persistence::{HighScore, HighScores, Scoring};
+use crate::common::replay::{Replay, Speed};
+use crate::common::result::{GameResult, Outcome};
+use crate::common::savegame::SaveSlot;
+use crate::common::session::Session;
+use crate::common::stats::LifetimeStats;
+use crate::common::terminal;
+use crate::common::theme;
+use crate::common::theme::Role;
 use crate::snake::snakemain::SnakeMain;
 use crate::spaceinvaders::spaceinvadersmain::SpaceInvadersMain;
 use crate::tetris::tetrismain::TetrisMain;
 use crate::wator::watormain::WatorMain;
 
 mod arkanoid;
+mod cli;
 mod common;
+mod server;
 mod snake;
 mod spaceinvaders;
 mod tetris;
 mod wator;
 
-// from https://stackoverflow.com/questions/55755552/what-is-the-rust-equivalent-to-a-try-catch-statement
-macro_rules! attempt { // `try` is a reserved keyword
-   (@recurse ($a:expr) { } catch ($e:ident) $b:block) => {
-      if let Err ($e) = $a $b
-   };
-   (@recurse ($a:expr) { $e:expr; $($tail:tt)* } $($handler:tt)*) => {
-      attempt!{@recurse ($a.and_then (|_| $e)) { $($tail)* } $($handler)*}
-   };
-   ({ $e:expr; $($tail:tt)* } $($handler:tt)*) => {
-      attempt!{@recurse ($e) { $($tail)* } $($handler)* }
-   };
-}
+const NAME_MAX_LEN: usize = 10;
+/// How long the menu waits for a key before the demos start.
+const DEMO_IDLE: Duration = Duration::from_secs(30);
+/// How long each game is shown in the demos.
+const DEMO_LENGTH: Duration = Duration::from_secs(20);
 
 pub trait Main<W: Write, R: Read> {
     fn name(&self) -> &'static str;
 
-    fn run(&self, stdout: &mut W, stdin: &mut R) -> io::Result<Option<u32>>;
+    fn run(&self, stdout: &mut W, stdin: &mut R) -> io::Result<GameResult>;
+
+    /// Continues the game in the save slot, which is emptied.
+    fn resume(&self, stdout: &mut W, stdin: &mut R) -> io::Result<GameResult>;
+
+    /// Plays back a recorded game, which must have been recorded by this game.
+    fn replay(&self, stdout: &mut W, stdin: &mut R, replay: Replay, speed: Speed) -> io::Result<GameResult>;
 
     fn high_scores(&self) -> io::Result<HighScores>;
+
+    fn stats(&self) -> io::Result<LifetimeStats>;
+
+    /// With the key bindings in use.
+    fn help(&self) -> io::Result<Help>;
+
+    fn save_slot(&self) -> SaveSlot;
+
+    /// Lets the computer play a Normal game, for the length or until a key is pressed, which returns false.
+    fn demo(&self, stdout: &mut W, stdin: &mut R, length: Duration) -> io::Result<bool>;
 }
 
 fn main() {
-    let mut stdout = stdout().into_raw_mode().unwrap();
-    let mut stdin = async_stdin();
+    let args = match cli::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprint!("{}\n\n{}", message, cli::usage());
+            process::exit(2);
+        }
+    };
+
+    if let Some(ref home) = args.home {
+        persistence::set_home(home.clone());
+    }
+
+    let mut replay = None;
+
+    match args.command {
+        Command::Help => {
+            print!("{}", cli::usage());
+            return;
+        }
+        Command::List => {
+            for main in mains::<io::Stdout, io::Stdin>(&args.options) {
+                println!("{:<16}{}", cli::normalize(main.name()), main.name());
+            }
+            return;
+        }
+        Command::Scores(game) => {
+            if let Err(e) = print_scores_plain(&args.options, game) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            return;
+        }
+        Command::Play(ref game) => {
+            if find_main::<io::Stdout, io::Stdin>(&args.options, game).is_none() {
+                eprint!("Unknown game {}\n\n{}", game, cli::usage());
+                process::exit(2);
+            }
+        }
+        Command::Replay(ref file, _) => match Replay::read(Path::new(file)) {
+            Ok(recorded) => {
+                if find_main::<io::Stdout, io::Stdin>(&recorded.options(), &recorded.game).is_none() {
+                    eprintln!("Unknown game {} in {}", recorded.game, file);
+                    process::exit(2);
+                }
+                replay = Some(recorded);
+            }
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", file, e);
+                process::exit(1);
+            }
+        },
+        Command::Serve(address, port) => {
+            if let Err(e) = server::serve(address, port, &args.options) {
+                eprintln!("Failed to serve on port {}: {}", port, e);
+                process::exit(1);
+            }
+            return;
+        }
+        Command::Menu => {}
+    }
+
+    // the terminal is put back as it was when the session is dropped, before any error is printed
+    let result = Session::start().and_then(|mut session| {
+        let mut stdin = async_stdin();
 
-    attempt! {{
-        run(&mut stdout, &mut stdin);
-    } catch(e) {
-        reset_status(&mut stdout).unwrap();
+        theme::load()?;
+        glyphs::load()?;
 
-        println!("Failed to run: {}", e);
-    }};
+        match args.command {
+            Command::Play(ref game) =>
+                run_main(&mut session, &mut stdin, find_main(&args.options, game).unwrap(), false),
+            Command::Replay(_, speed) => run_replay(&mut session, &mut stdin, replay.take().unwrap(), speed),
+            _ => run(&mut session, &mut stdin, &args.options)
+        }
+    });
 
-    reset_status(&mut stdout).unwrap();
+    if let Err(e) = result {
+        eprintln!("Failed to run: {}", e);
+        process::exit(1);
+    }
 }
 
-fn reset_status<W: 'static>(stdout: &mut W) -> io::Result<()> where W: Write {
-    write!(stdout,
-           "{}{}\r\n",
-           termion::cursor::Show,
-           termion::style::Reset)?;
-    stdout.flush()
+fn mains<W: 'static + Write, R: 'static + Read>(options: &GameOptions) -> Vec<Box<dyn Main<W, R>>> {
+    vec!(
+        Box::new(ArkanoidMain::new(*options)),
+        Box::new(SnakeMain::new(*options)),
+        Box::new(SpaceInvadersMain::new(*options)),
+        Box::new(TetrisMain::new(*options)),
+        Box::new(TetrisMain::sprint(*options)),
+        Box::new(WatorMain::new(*options))
+    )
 }
 
-fn run<W: 'static, R: 'static>(stdout: &mut W, stdin: &mut R) -> io::Result<()> where W: Write, R: Read {
+fn find_main<W: 'static + Write, R: 'static + Read>(options: &GameOptions, name: &str) -> Option<Box<dyn Main<W, R>>> {
+    mains(options).into_iter().find(|main| cli::matches(main.name(), name))
+}
+
+fn run<W: 'static + Write, R: 'static + Read>(stdout: &mut W, stdin: &mut R, options: &GameOptions) -> io::Result<()> {
+    let mut options = *options;
+    let mut selected = 0;
+
     loop {
         write!(stdout,
-               "{}{}{}{}{}Console games{}\r\n\r\nPress {}Esc{} to exit",
+               "{}{}{}{}Console games{}\r\n\r\nPress {}Esc{} to exit",
                termion::cursor::Hide,
                termion::clear::All,
                termion::cursor::Goto(1, 1),
-               color::Fg(color::LightWhite),
-               color::Bg(color::Green),
+               Role::Title,
                termion::style::Reset,
-               color::Fg(color::LightWhite),
+               Role::Emphasis,
                termion::style::Reset).unwrap();
 
-        let mains: Vec<Box<dyn Main<W, R>>> =
-            vec!(
-                Box::new(ArkanoidMain::new()),
-                Box::new(SnakeMain::new()),
-                Box::new(SpaceInvadersMain::new()),
-                Box::new(TetrisMain::new()),
-                Box::new(WatorMain::new())
-            );
+        let mains = mains(&options);
 
-        let menu = mains.iter().map(|main| main.name()).collect();
+        let mut menu: Vec<&str> = mains.iter().map(|main| main.name()).collect();
+        menu.push("Statistics");
+        menu.push("Settings");
 
-        let choice = common::menu::choose(stdout, stdin, &menu, 1, 5).unwrap();
+        let choice = common::menu::choose_until_idle(stdout, stdin, &menu, selected, 1, 5, Some(DEMO_IDLE))?;
 
-        if let Some(index) = choice {
-            run_main(stdout, stdin, mains.into_iter().enumerate().find(|(i, _main)| *i == index as usize).unwrap().1)?;
-        } else {
+        match choice {
+            Choice::Item(index) | Choice::Idle(index) => selected = index,
+            Choice::Cancelled => {}
+        }
+
+        match choice {
+            Choice::Item(index) if index as usize == mains.len() => show_stats(stdout, stdin, mains)?,
+            Choice::Item(index) if index as usize == mains.len() + 1 => change_settings(stdout, stdin, &mut options)?,
+            Choice::Item(index) => if let Some(difficulty) = choose_difficulty(stdout, stdin, options.difficulty)? {
+                options.difficulty = difficulty;
+                run_main(stdout, stdin, self::mains(&options).into_iter().nth(index as usize).unwrap(), true)?
+            },
+            Choice::Idle(_) => run_demos(stdout, stdin, &mains)?,
+            Choice::Cancelled => if common::menu::confirm(stdout, stdin, "Exit console games?")? {
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows the demos of the games one after the other, until a key is pressed.
+fn run_demos<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, mains: &[Box<dyn Main<W, R>>]) -> io::Result<()> {
+    for main in mains.iter().cycle() {
+        if !main.demo(stdout, stdin, DEMO_LENGTH)? {
+            break;
+        }
+
+        // a game which doesn't fit in the terminal is skipped at once, so we may not be in any demo for a while
+        if stdin.keys().next().is_some() {
             break;
         }
+
+        thread::sleep(Duration::from_millis(100));
     }
 
     Ok(())
 }
 
-fn run_main<W, R>(stdout: &mut W, stdin: &mut R, main: Box<dyn Main<W, R>>) -> io::Result<()> where W: Write, R: Read {
-    let scores = main.high_scores()?;
+/// Asks the difficulty of the next game, starting on the last one; Custom asks its speed too.
+fn choose_difficulty<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, last: Difficulty) -> io::Result<Option<Difficulty>> {
+    let (x, y) = common::menu::centered(20, 8);
+
+    common::menu::print_box(stdout, x, y, 20, 8, "Difficulty")?;
+
+    let selected = Difficulty::PRESETS.iter().position(|&preset| preset == last).unwrap_or(3);
 
-    print_scores(stdout, scores, None)?;
+    let choices = ["Easy", "Normal", "Hard", "Custom"];
+
+    let difficulty = match common::menu::choose_from(stdout, stdin, &choices, selected as u8, x + 3, y + 3)? {
+        Some(index) if (index as usize) < Difficulty::PRESETS.len() => Difficulty::PRESETS[index as usize],
+        Some(_) => {
+            let speed = match last {
+                Difficulty::Custom(speed) => speed,
+                _ => 100
+            };
+            let title = format!("Speed, {} to {}% of Normal:", CUSTOM_SPEEDS.0, CUSTOM_SPEEDS.1);
+
+            match common::menu::input(stdout, stdin, &title, &speed.to_string(), 3)? {
+                Some(speed) => match speed.trim().parse() {
+                    Ok(difficulty @ Difficulty::Custom(_)) => difficulty,
+                    _ => {
+                        let line = format!("{} is not a speed from {} to {}.", speed, CUSTOM_SPEEDS.0, CUSTOM_SPEEDS.1);
+                        common::menu::message(stdout, stdin, "Invalid speed", &[line])?;
+                        return Ok(None);
+                    }
+                },
+                None => return Ok(None)
+            }
+        }
+        None => return Ok(None)
+    };
+
+    Ok(Some(difficulty))
+}
+
+/// Shows the statistics of a game chosen by the player.
+fn show_stats<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, mains: Vec<Box<dyn Main<W, R>>>) -> io::Result<()> {
+    const HISTORY_DAYS: u32 = 7;
+    const BAR_WIDTH: u16 = 40;
+
+    let names: Vec<String> = mains.iter().map(|main| main.name().to_string()).collect();
+
+    let main = match common::menu::list(stdout, stdin, "Statistics", &names, 10)? {
+        Some(index) => &mains[index],
+        None => return Ok(())
+    };
+
+    let stats = main.stats()?;
+    let scores = main.high_scores()?;
+    let scoring = scores.scoring();
+    let format = |score: Option<u32>| score.map(|score| scoring.format(score)).unwrap_or_else(|| "-".to_string());
+    let seconds = stats.play_time().as_secs();
+
+    let mut lines = vec!(
+        ("Games played".to_string(), stats.games().to_string()),
+        ("Play time".to_string(), format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)),
+        ("Average score".to_string(), format(stats.average())),
+        ("Median score".to_string(), format(stats.median())),
+        ("Best score".to_string(), format(scores.best())),
+        ("Most days in a row".to_string(), stats.best_day_streak().to_string()),
+    );
+    if stats.best_clear_streak() > 0 {
+        lines.push(("Most clears in a row".to_string(), stats.best_clear_streak().to_string()));
+    }
+    lines.extend(stats.totals().iter().map(|(name, total)| (capitalize(name), total.to_string())));
+    lines.extend(stats.longest().iter().map(|(name, longest)| (capitalize(name), longest.to_string())));
 
     write!(stdout,
-           "{}Press {}p{} to play.",
-           termion::cursor::Goto(1, 20),
-           color::Fg(color::LightWhite),
+           "{}{}{}{} statistics, {}{}",
+           termion::clear::All,
+           termion::cursor::Goto(10, 1),
+           Role::Title,
+           main.name(),
+           scores.difficulty(),
            termion::style::Reset)?;
 
+    for (i, (label, value)) in lines.iter().enumerate() {
+        write!(stdout, "{}{:<30}{}", termion::cursor::Goto(1, 3 + i as u16), label, value)?;
+    }
+
+    let y = 4 + lines.len() as u16;
+    let history: Vec<(String, u32)> = stats.history(Local::today().naive_local(), HISTORY_DAYS).iter()
+        .map(|(date, day)| (date.format("%a %m-%d").to_string(), day.games))
+        .collect();
+
+    write!(stdout, "{}Games per day", termion::cursor::Goto(1, y))?;
+    common::menu::bar_chart(stdout, 1, y + 1, &history, BAR_WIDTH)?;
+
+    write!(stdout,
+           "{}Press any key to return to menu.",
+           termion::cursor::Goto(1, y + 2 + HISTORY_DAYS as u16))?;
+
     stdout.flush()?;
 
-    wait_for_key_async(stdin, Key::Char('p'))?;
+    wait_for_any_key_async(stdin).map(|_| ())
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
 
-    'outer: loop {
-        let result = main.run(stdout, stdin)?;
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// Changes the options given on the command line, until the games are closed.
+fn change_settings<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, options: &mut GameOptions) -> io::Result<()> {
+    let mut settings = [
+        Setting { label: "Board width", value: SettingValue::Slider { value: options.width.map(u32::from), min: 4, max: 80 } },
+        Setting { label: "Board height", value: SettingValue::Slider { value: options.height.map(u32::from), min: 4, max: 40 } },
+        Setting { label: "Random games", value: SettingValue::Check(options.seed.is_none()) },
+        Setting { label: "Seed when not random", value: SettingValue::Text(options.seed.map(|seed| seed.to_string()).unwrap_or_default()) },
+        Setting { label: "Theme", value: SettingValue::Choice {
+            options: theme::THEMES.iter().map(|theme| theme.name).collect(),
+            index: theme::THEMES.iter().position(|theme| *theme == theme::current().theme).unwrap_or(0),
+        } },
+        Setting { label: "Glyphs", value: SettingValue::Choice {
+            options: glyphs::ALL.iter().map(|glyphs| glyphs.name()).collect(),
+            index: glyphs::ALL.iter().position(|glyphs| *glyphs == glyphs::current()).unwrap_or(0),
+        } },
+    ];
+
+    if !common::menu::settings(stdout, stdin, "Settings", &mut settings)? {
+        return Ok(());
+    }
 
-        if let Some(score) = result {
-            let mut scores = main.high_scores()?;
+    if let [Setting { value: SettingValue::Slider { value: width, .. }, .. },
+    Setting { value: SettingValue::Slider { value: height, .. }, .. },
+    Setting { value: SettingValue::Check(random), .. },
+    Setting { value: SettingValue::Text(seed), .. },
+    Setting { value: SettingValue::Choice { index: theme, .. }, .. },
+    Setting { value: SettingValue::Choice { index: glyphs, .. }, .. }] = &settings {
+        // a remote player's settings last for their session, instead of changing everyone's
+        let remote = terminal::is_remote();
+
+        if theme::THEMES[*theme] != theme::current().theme {
+            if remote { theme::set(theme::THEMES[*theme]) } else { theme::save(theme::THEMES[*theme])? }
+        }
 
-            let added = scores.add(score);
+        if glyphs::ALL[*glyphs] != glyphs::current() {
+            if remote { glyphs::set(glyphs::ALL[*glyphs]) } else { glyphs::save(glyphs::ALL[*glyphs])? }
+        }
 
-            scores.save()?;
+        options.width = width.map(|width| width as u8);
+        options.height = height.map(|height| height as u8);
+        options.seed = if *random { None } else { seed.parse().ok() };
 
-            print_scores(stdout, scores, added.map(|score| score.time()))?;
+        if !*random && options.seed.is_none() {
+            common::menu::message(stdout, stdin, "Invalid seed",
+                                  &[format!("{} is not a number,", seed), "the games will be random.".to_string()])?;
+        }
+    }
 
+    Ok(())
+}
+
+/// When `wait_to_start` is false the game starts straight away, as when it's given on the command line.
+fn run_main<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, main: Box<dyn Main<W, R>>, wait_to_start: bool) -> io::Result<()> {
+    let mut resume = false;
+
+    if wait_to_start {
+        let scores = read_scores(stdout, stdin, main.as_ref())?;
+
+        print_scores(stdout, scores, None)?;
+
+        print_help(stdout, &main.help()?)?;
+
+        // remote players can't save, and the saved games are not theirs
+        if !terminal::is_remote() && main.save_slot().exists() {
+            match common::menu::choose(stdout, stdin, &["Continue", "New game"], 1, 20)? {
+                Some(0) => resume = true,
+                Some(_) => {}
+                None => return Ok(())
+            }
+        } else {
             write!(stdout,
-                   "{}Game over! \n\rScore: {}\n\r\n\rPress {}p{} to play again, {}Esc{} exit to return to menu.",
-                   termion::cursor::Goto(1, 15),
-                   score,
-                   color::Fg(color::LightWhite),
-                   termion::style::Reset,
-                   color::Fg(color::LightWhite),
+                   "{}Press {}p{} to play.",
+                   termion::cursor::Goto(1, 20),
+                   Role::Emphasis,
                    termion::style::Reset)?;
 
             stdout.flush()?;
 
+            wait_for_key_async(stdin, Key::Char('p'))?;
+        }
+    }
+
+    'outer: loop {
+        let result = if resume { main.resume(stdout, stdin)? } else { main.run(stdout, stdin)? };
+        resume = false;
+
+        if result.is_over() {
+            let mut scores = read_scores(stdout, stdin, main.as_ref())?;
+            let scoring = scores.scoring();
+
+            let added = match result.score {
+                Some(points) if scores.qualifies(points) => {
+                    let mut score = HighScore::new(points, &result.mode, result.duration, &result.stats);
+
+                    ask_name(stdout, stdin, &mut score)?;
+
+                    let added = scores.add(score);
+                    scores.save()?;
+                    added
+                }
+                _ => None
+            };
+
+            main.stats()?.record(&result, Local::today().naive_local())?;
+
+            print_scores(stdout, scores, added.as_ref().map(HighScore::time))?;
+
+            print_summary(stdout, &result, scoring, added.is_some())?;
+
             'wait_for_key: loop {
                 if let Some(key_or_error) = stdin.keys().next() {
                     let key = key_or_error?;
@@ -170,27 +474,219 @@ fn run_main<W, R>(stdout: &mut W, stdin: &mut R, main: Box<dyn Main<W, R>>) -> i
     Ok(())
 }
 
+fn print_help<W: Write>(stdout: &mut W, help: &Help) -> io::Result<()> {
+    const MAX_LINES: usize = 5;
+
+    let (width, _) = common::terminal::size();
+
+    for (i, line) in help.summary(width.saturating_sub(1).max(20) as usize).iter().take(MAX_LINES).enumerate() {
+        write!(stdout, "{}{}", termion::cursor::Goto(1, 14 + i as u16), line)?;
+    }
+
+    Ok(())
+}
+
+/// How the game ended, under the high scores.
+fn print_summary<W: Write>(stdout: &mut W, result: &GameResult, scoring: Scoring, high_score: bool) -> io::Result<()> {
+    let title = if result.outcome == Outcome::Cleared { "Cleared!" } else { "Game over!" };
+    let score = result.score.map(|score| scoring.format(score)).unwrap_or_else(|| "-".to_string());
+    let seconds = result.duration.as_secs();
+    let stats: Vec<String> = result.stats.iter().map(|(stat, value)| format!("{} {}", stat, value)).collect();
+
+    write!(stdout,
+           "{}{}{}{}{}",
+           termion::cursor::Goto(1, 15),
+           Role::Emphasis,
+           title,
+           if high_score { "  New high score!" } else { "" },
+           termion::style::Reset)?;
+
+    write!(stdout,
+           "{}Score: {}   Time: {}:{:02}   Mode: {}{}{}",
+           termion::cursor::Goto(1, 16),
+           score,
+           seconds / 60,
+           seconds % 60,
+           result.mode,
+           termion::cursor::Goto(1, 17),
+           stats.join(", "))?;
+
+    write!(stdout,
+           "{}Press {}p{} to play again, {}Esc{} to return to menu.",
+           termion::cursor::Goto(1, 19),
+           Role::Emphasis,
+           termion::style::Reset,
+           Role::Emphasis,
+           termion::style::Reset)?;
+
+    stdout.flush()
+}
+
+/// Tells the player when their scores were corrupt, and could not be kept.
+fn read_scores<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, main: &dyn Main<W, R>) -> io::Result<HighScores> {
+    let scores = main.high_scores()?;
+
+    if let Some(backup) = scores.backup() {
+        common::menu::message(stdout, stdin, "High scores lost",
+                              &["The high scores file was corrupt, it was moved to".to_string(),
+                                backup.display().to_string()])?;
+    }
+
+    Ok(scores)
+}
+
+/// Asks the name of the player who made a high score, remembering it for the next time, unless the player is
+/// remote: the next one may be someone else.
+fn ask_name<W: Write, R: Read>(stdout: &mut W, stdin: &mut R, score: &mut HighScore) -> io::Result<()> {
+    let remote = terminal::is_remote();
+    let last_name = if remote { None } else { persistence::last_name()? }.unwrap_or_default();
+
+    let name = common::menu::input(stdout, stdin, "New high score! Your name:", &last_name, NAME_MAX_LEN)?;
+
+    if let Some(name) = name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty()) {
+        if !remote {
+            persistence::save_last_name(&name)?;
+        }
+        score.set_name(&name);
+    }
+
+    Ok(())
+}
+
+fn run_replay<W: 'static + Write, R: 'static + Read>(stdout: &mut W, stdin: &mut R, replay: Replay, speed: Speed) -> io::Result<()> {
+    let main = find_main(&replay.options(), &replay.game).unwrap();
+
+    let result = main.replay(stdout, stdin, replay, speed)?;
+    let scoring = main.high_scores()?.scoring();
+
+    let lines = match result.score {
+        Some(score) if result.is_over() => vec!(format!("Game over, score {}.", scoring.format(score))),
+        _ if result.is_over() => vec!("Game over.".to_string()),
+        _ => vec!("The game was quit here.".to_string())
+    };
+
+    common::menu::message(stdout, stdin, "Replay over", &lines)
+}
+
 fn print_scores<W: Write>(stdout: &mut W, scores: HighScores, highlight: Option<DateTime<Local>>) -> io::Result<()> {
     write!(stdout,
-           "{}{}{}{}High scores{}",
+           "{}{}{}High scores, {}{}",
            termion::clear::All,
            termion::cursor::Goto(10, 1),
-           color::Fg(color::LightWhite),
-           color::Bg(color::Green),
+           Role::Title,
+           scores.difficulty(),
            termion::style::Reset)?;
 
-    let mut y = 3;
-    for score in scores.entries().iter() {
+    for (i, score) in scores.entries().iter().enumerate() {
         if Some(score.time()) == highlight {
             write!(stdout, "{}",
-                   color::Fg(color::Green))?;
+                   Role::Highlight)?;
         }
         write!(stdout,
                "{}{}{}",
-               termion::cursor::Goto(10, y),
-               score.score(),
+               termion::cursor::Goto(1, 3 + i as u16),
+               format_score(i, score, scores.scoring()),
                termion::style::Reset)?;
-        y += 1;
     }
     Ok(())
 }
+
+/// A line of the high scores table: rank, name, score, mode, duration, date and the statistics of the game.
+fn format_score(rank: usize, score: &HighScore, scoring: Scoring) -> String {
+    let name = if score.name().is_empty() { "-" } else { score.name() };
+    let seconds = score.duration().as_secs();
+    let stats: Vec<String> = score.stats().iter().map(|(stat, value)| format!("{} {}", stat, value)).collect();
+
+    format!("{:>3}. {:<width$} {:>11}  {:<7} {:>3}:{:02}  {}  {}",
+            rank + 1, name, scoring.format(score.score()), score.mode(), seconds / 60, seconds % 60,
+            score.time().format("%Y-%m-%d %H:%M"), stats.join(", "), width = NAME_MAX_LEN)
+}
+
+/// Prints the high scores as plain text, for the command line.
+fn print_scores_plain(options: &GameOptions, game: Option<String>) -> io::Result<()> {
+    let mains: Vec<Box<dyn Main<io::Stdout, io::Stdin>>> = match game {
+        Some(game) => match find_main(options, &game) {
+            Some(main) => vec!(main),
+            None => return Err(io::Error::other(format!("Unknown game {}", game)))
+        },
+        None => mains(options)
+    };
+
+    for main in mains {
+        let scores = main.high_scores()?;
+
+        println!("{}, {}", main.name(), scores.difficulty());
+
+        if scores.entries().is_empty() {
+            println!("  no scores yet");
+        }
+
+        for (i, score) in scores.entries().iter().enumerate() {
+            println!("{}", format_score(i, score, scores.scoring()));
+        }
+
+        println!();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+use crate::common::headless::{DOWN, ENTER, ESC, Headless, LEFT, Script, UP, VirtualTerminal};
+
+#[test]
+fn a_high_score_should_be_named_and_the_name_remembered_for_the_next_one() {
+    let options = GameOptions { width: Some(10), height: Some(6), seed: Some(1), ..GameOptions::default() };
+    let headless = Headless::new(80, 24);
+    let game = || Script::new()
+        .keys(&[UP]).wait(400)
+        .keys(&[LEFT]).wait(800)
+        .keys(&[DOWN]).wait(1000);
+
+    let (result, _) = headless.run(game().keys(&["A", "d", "a", ENTER, ESC]), |terminal, script|
+        run_main(terminal, script, Box::new(SnakeMain::new(options)), false));
+    result.unwrap();
+    let (result, terminal) = headless.run(game().keys(&["\x7f", "m", ENTER, ESC]), |terminal, script|
+        run_main(terminal, script, Box::new(SnakeMain::new(options)), false));
+    result.unwrap();
+
+    let (scores, _) = headless.run(Script::new(), |_, _| Main::<VirtualTerminal, Script>::high_scores(&SnakeMain::new(options)));
+    let scores = scores.unwrap();
+    let names: Vec<String> = scores.entries().iter().map(|score| score.name().to_string()).collect();
+    assert_eq!(vec!("Ada", "Adm"), names);
+    assert!(terminal.snapshot().contains("Adm"));
+    assert!(terminal.snapshot().contains("Game over!  New high score!"));
+}
+
+#[test]
+fn the_games_played_to_the_end_should_be_counted_in_the_statistics() {
+    let options = GameOptions { width: Some(10), height: Some(6), seed: Some(1), ..GameOptions::default() };
+    let headless = Headless::new(80, 24);
+    let game = Script::new()
+        .keys(&[UP]).wait(400)
+        .keys(&[LEFT]).wait(800)
+        .keys(&[DOWN]).wait(1000);
+
+    let (result, _) = headless.run(game.keys(&[ESC, ESC]), |terminal, script|
+        run_main(terminal, script, Box::new(SnakeMain::new(options)), false));
+    result.unwrap();
+    let (result, terminal) = headless.run(Script::new().keys(&[DOWN, ENTER, " "]), |terminal, script|
+        show_stats(terminal, script, mains(&options)));
+    result.unwrap();
+
+    let snapshot = terminal.snapshot();
+    assert!(snapshot.lines().next().unwrap().ends_with("Normal"));
+    assert!(snapshot.contains("Games played                  1"));
+    assert!(snapshot.contains("Food eaten                    3"));
+    assert!(snapshot.contains(&format!("{} 1", "█".repeat(40))));
+}
+
+#[test]
+fn a_custom_difficulty_should_ask_its_speed() {
+    let headless = Headless::new(80, 24);
+    let script = Script::new().keys(&[DOWN, DOWN, ENTER, "\x7f", "\x7f", "\x7f", "1", "5", "0", ENTER]);
+
+    let (difficulty, _) = headless.run(script, |terminal, script| choose_difficulty(terminal, script, Difficulty::Normal));
+
+    assert_eq!(Some(Difficulty::Custom(150)), difficulty.unwrap());
+}
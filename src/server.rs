@@ -0,0 +1,191 @@
+use std::{io, thread};
+use std::io::{BufWriter, ErrorKind, Read, Write};
+use std::net::{IpAddr, Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use crate::common::glyphs;
+use crate::common::options::GameOptions;
+use crate::common::session;
+use crate::common::telnet::{Decoder, Event, NEGOTIATION};
+use crate::common::terminal;
+use crate::common::terminal::Remote;
+use crate::common::theme;
+use crate::common::theme::Depth;
+
+/// Lets players connect with telnet until we are stopped, each to the menu in a thread of their own. The high
+/// scores and statistics are shared with the local players: their files are locked while they are updated.
+pub fn serve(address: IpAddr, port: u16, options: &GameOptions) -> io::Result<()> {
+    let listener = TcpListener::bind((address, port))?;
+
+    println!("Serving the games on {} port {}, e.g. telnet localhost {}", address, port, port);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept a player: {}", e);
+                continue;
+            }
+        };
+
+        let options = *options;
+
+        thread::spawn(move || {
+            let peer = stream.peer_addr().map(|peer| peer.to_string()).unwrap_or_else(|_| "A player".to_string());
+            println!("{} connected", peer);
+
+            match play(stream, &options) {
+                Ok(()) => println!("{} left", peer),
+                Err(e) => println!("{} left: {}", peer, e)
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Runs the menu for the client, in character mode, until it exits or is gone.
+fn play(stream: TcpStream, options: &GameOptions) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+
+    let remote = Remote::default();
+    let mut input = Input { keys: listen(stream.try_clone()?, remote.clone()) };
+
+    terminal::set_remote(remote);
+    // we don't ask the client which colors it has
+    theme::set_depth(Depth::Ansi);
+    theme::load()?;
+    glyphs::load()?;
+
+    let mut out = BufWriter::new(stream.try_clone()?);
+    out.write_all(&NEGOTIATION)?;
+    session::set_up(&mut out)?;
+    out.flush()?;
+
+    let result = crate::run(&mut out, &mut input, options);
+
+    // the client may be gone already
+    let _ = session::put_back(&mut out).and_then(|_| out.flush());
+    let _ = stream.shutdown(Shutdown::Both);
+
+    result
+}
+
+/// The keys of a client, which never waits, as `termion::async_stdin`. Fails once the client is gone, so the
+/// games stop.
+struct Input {
+    keys: Receiver<u8>,
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+
+        while read < buf.len() {
+            match self.keys.try_recv() {
+                Ok(key) => {
+                    buf[read] = key;
+                    read += 1;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) if read == 0 =>
+                    return Err(io::Error::new(ErrorKind::ConnectionAborted, "the player is gone")),
+                Err(TryRecvError::Disconnected) => break
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+/// Reads what the client sends in a thread of its own: the keys go to the receiver, and its window size to the
+/// remote terminal. An interrupt closes the connection.
+fn listen(mut stream: TcpStream, remote: Remote) -> Receiver<u8> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut decoder = Decoder::new();
+        let mut buf = [0; 256];
+
+        loop {
+            let read = match stream.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read
+            };
+
+            let (keys, events) = decoder.decode(&buf[..read]);
+
+            for event in events {
+                match event {
+                    Event::Size(width, height) => remote.resize(width, height),
+                    Event::Interrupt => {
+                        let _ = stream.shutdown(Shutdown::Both);
+                        return;
+                    }
+                }
+            }
+
+            if keys.into_iter().any(|key| sender.send(key).is_err()) {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+use std::time::Duration;
+
+#[cfg(test)]
+use crate::common::headless::Headless;
+#[cfg(test)]
+use crate::common::persistence::TEST_HOME;
+
+/// Reads what the server sends until the text shows.
+#[cfg(test)]
+fn read_until(client: &mut TcpStream, received: &mut Vec<u8>, text: &str) {
+    let mut buf = [0; 4096];
+
+    while !String::from_utf8_lossy(received).contains(text) {
+        let read = client.read(&mut buf).unwrap();
+        assert!(read > 0, "the server left before {:?}", text);
+        received.extend_from_slice(&buf[..read]);
+    }
+}
+
+#[test]
+fn a_telnet_client_should_get_the_menu_in_its_window_and_be_able_to_exit() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = thread::spawn(move || {
+        let headless = Headless::new(80, 24);
+        TEST_HOME.with(|home| *home.borrow_mut() = Some(headless.home().to_path_buf()));
+
+        let (stream, _) = listener.accept().unwrap();
+        let result = play(stream, &GameOptions::default());
+
+        (result.map_err(|e| e.to_string()), terminal::size())
+    });
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let mut received = Vec::new();
+
+    client.write_all(&[255, 250, 31, 0, 100, 0, 30, 255, 240]).unwrap();
+    read_until(&mut client, &mut received, "Console games");
+    assert!(received.starts_with(&NEGOTIATION));
+
+    client.write_all(b"\x1b").unwrap();
+    read_until(&mut client, &mut received, "Exit console games?");
+    client.write_all(b"\x1b[B").unwrap();
+    thread::sleep(Duration::from_millis(300));
+    client.write_all(b"\r\0").unwrap();
+
+    let (result, size) = server.join().unwrap();
+
+    assert_eq!(Ok(()), result);
+    assert_eq!((100, 30), size);
+}
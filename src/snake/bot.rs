@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+
+use crate::common::keys::Action;
+use crate::common::point::{Direction, Point};
+use crate::snake::snake::Snake;
+
+const DIRECTIONS: [(Direction, Action); 4] = [(Direction::North, Action::MoveUp), (Direction::South, Action::MoveDown),
+    (Direction::East, Action::MoveRight), (Direction::West, Action::MoveLeft)];
+
+/// Heads along a shortest path to the nearest food, unless the snake would then be left with less room than
+/// its length, or there is no path; then it heads where there is the most room.
+pub fn actions(snake: &Snake) -> Vec<Action> {
+    let blocked = Blocked::new(snake);
+    let head = snake.last();
+
+    let safe: Vec<(Point, Action)> = DIRECTIONS.iter()
+        .map(|(direction, action)| (head.mv(direction), *action))
+        .filter(|(point, _)| !blocked.at(point))
+        .collect();
+
+    if let Some(first) = first_step_to_food(snake, &blocked) {
+        if let Some((point, action)) = safe.iter().find(|(point, _)| point.x == first.x && point.y == first.y) {
+            if room(&blocked, point) >= snake.length() as usize {
+                return vec!(*action);
+            }
+        }
+    }
+
+    safe.iter()
+        .max_by_key(|(point, _)| room(&blocked, point))
+        .map(|(_, action)| vec!(*action))
+        .unwrap_or_default()
+}
+
+/// The walls and the body of the snake, where its head can't go.
+struct Blocked {
+    width: u8,
+    height: u8,
+    cells: Vec<bool>,
+}
+
+impl Blocked {
+    fn new(snake: &Snake) -> Blocked {
+        let mut blocked = Blocked {
+            width: snake.width(),
+            height: snake.height(),
+            cells: vec!(false; snake.width() as usize * snake.height() as usize),
+        };
+
+        for point in snake.points() {
+            if let Some(index) = blocked.index(point) {
+                blocked.cells[index] = true;
+            }
+        }
+
+        blocked
+    }
+
+    fn index(&self, point: &Point) -> Option<usize> {
+        if point.x < 0 || point.y < 0 || point.x >= self.width as i8 || point.y >= self.height as i8 {
+            return None;
+        }
+
+        Some(point.y as usize * self.width as usize + point.x as usize)
+    }
+
+    fn at(&self, point: &Point) -> bool {
+        self.index(point).map(|index| self.cells[index]).unwrap_or(true)
+    }
+}
+
+/// Breadth first from the head: where it first goes on the way to the nearest food.
+fn first_step_to_food(snake: &Snake, blocked: &Blocked) -> Option<Point> {
+    let head = snake.last();
+    let mut first: Vec<Option<Point>> = vec!(None; blocked.cells.len());
+    let mut queue = VecDeque::new();
+
+    for (direction, _) in &DIRECTIONS {
+        let point = head.mv(direction);
+
+        if let Some(index) = blocked.index(&point).filter(|&index| !blocked.cells[index] && first[index].is_none()) {
+            first[index] = Some(point.clone());
+            queue.push_back(point);
+        }
+    }
+
+    while let Some(point) = queue.pop_front() {
+        let step = first[blocked.index(&point)?].clone();
+
+        if snake.food().iter().any(|food| food.x == point.x && food.y == point.y) {
+            return step;
+        }
+
+        for (direction, _) in &DIRECTIONS {
+            let next = point.mv(direction);
+
+            if let Some(index) = blocked.index(&next).filter(|&index| !blocked.cells[index] && first[index].is_none()) {
+                first[index] = step.clone();
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// How many cells can be reached from the point.
+fn room(blocked: &Blocked, start: &Point) -> usize {
+    let mut seen = vec!(false; blocked.cells.len());
+    let mut stack = vec!(start.clone());
+    let mut count = 0;
+
+    while let Some(point) = stack.pop() {
+        match blocked.index(&point) {
+            Some(index) if !blocked.cells[index] && !seen[index] => {
+                seen[index] = true;
+                count += 1;
+                stack.extend(DIRECTIONS.iter().map(|(direction, _)| point.mv(direction)));
+            }
+            _ => {}
+        }
+    }
+
+    count
+}
+
+#[cfg(test)]
+use crate::common::random::new_rng;
+
+#[test]
+fn the_bot_should_eat_before_it_runs_out_of_room() {
+    let mut snake = Snake::new(12, 8, Direction::East, 1, new_rng(3));
+
+    for _ in 0..300 {
+        for action in actions(&snake) {
+            let (direction, _) = DIRECTIONS.iter().find(|(_, other)| *other == action).unwrap();
+            snake = snake.mv(direction.clone());
+        }
+
+        match snake.next() {
+            Some(next) => snake = next,
+            None => break
+        }
+    }
+
+    assert!(snake.eaten() >= 10, "{} eaten", snake.eaten());
+}
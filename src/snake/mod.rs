@@ -1,2 +1,4 @@
+mod bot;
+#[allow(clippy::module_inception)]
 mod snake;
 pub mod snakemain;
\ No newline at end of file
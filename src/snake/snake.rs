@@ -1,11 +1,13 @@
-use std::io;
-use std::io::Write;
-
 use rand::Rng;
 
-use crate::common::ioutils::print_border;
+use crate::common::canvas::Canvas;
+use crate::common::consolecolor::Color;
 use crate::common::point::{Direction, Point};
+use crate::common::random::GameRng;
+
+pub const FOOD_SCORE: u32 = 100;
 
+#[derive(Serialize, Deserialize)]
 pub struct Snake {
     width: u8,
     height: u8,
@@ -13,19 +15,19 @@ pub struct Snake {
     points: Vec<Point>,
     food: Vec<Point>,
     score: u32,
+    rng: GameRng,
 }
 
 impl Snake {
-    pub fn new(width: u8, height: u8, direction: Direction, food_count: u8) -> Snake {
+    pub fn new(width: u8, height: u8, direction: Direction, food_count: u8, mut rng: GameRng) -> Snake {
         let mut food = Vec::new();
-        let mut rng = rand::thread_rng();
 
         for _i in 0..food_count {
             food.push(Point::new(rng.gen_range(0, width) as i8,
                                  rng.gen_range(0, height) as i8));
         }
 
-        Snake { width, height, points: vec!(Point::new(width as i8 / 2, height as i8 / 2)), direction, food, score: 0 }
+        Snake { width, height, points: vec!(Point::new(width as i8 / 2, height as i8 / 2)), direction, food, score: 0, rng }
     }
 
     pub fn last(&self) -> &Point {
@@ -40,6 +42,7 @@ impl Snake {
             direction,
             food: self.food.clone(),
             score: self.score,
+            rng: self.rng.clone(),
         }
     }
 
@@ -60,7 +63,7 @@ impl Snake {
             .find(|(_i, point)| point.x == self.last().x && point.y == self.last().y)
             .map(|(i, _point)| i);
 
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.clone();
 
         let mut points: Vec<Point> = self.points.clone();
 
@@ -70,7 +73,7 @@ impl Snake {
             food.remove(food_index);
             food.push(Point::new(rng.gen_range(0, self.width) as i8,
                                  rng.gen_range(0, self.height) as i8));
-            score += 100;
+            score += FOOD_SCORE;
         } else {
             points.remove(0);
         }
@@ -84,21 +87,66 @@ impl Snake {
             direction: self.direction.clone(),
             food,
             score,
+            rng,
         })
     }
 
-    pub fn print<W: Write>(&self, term: &mut W, x: u16, y: u16) -> io::Result<()> {
+    pub fn print<C: Canvas>(&self, canvas: &mut C, x: u16, y: u16) {
         for point in self.food.iter() {
-            write!(term, "{}.", termion::cursor::Goto(point.x as u16 + 2, point.y as u16 + 3))?;
+            canvas.put(point.x as u16 + x + 1, point.y as u16 + y + 1, '.', Color::DefaultColor, Color::DefaultColor);
         }
 
         for point in self.points.iter() {
-            write!(term, "{}#", termion::cursor::Goto(point.x as u16 + x + 1, point.y as u16 + y + 1))?;
+            canvas.put(point.x as u16 + x + 1, point.y as u16 + y + 1, '#', Color::DefaultColor, Color::DefaultColor);
         }
-        print_border(term, x, y, self.width as u16 + 2, self.height as u16 + 2)
+
+        canvas.border(x, y, self.width as u16 + 2, self.height as u16 + 2)
     }
 
     pub fn score(&self) -> u32 {
         self.score
     }
-}
\ No newline at end of file
+
+    pub fn length(&self) -> u32 {
+        self.points.len() as u32
+    }
+
+    /// Food eaten so far.
+    pub fn eaten(&self) -> u32 {
+        self.score / FOOD_SCORE
+    }
+
+    /// From the tail to the head.
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    pub fn food(&self) -> &[Point] {
+        &self.food
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+}
+
+#[cfg(test)]
+use crate::common::canvas::Buffer;
+#[cfg(test)]
+use crate::common::random::new_rng;
+
+#[test]
+fn the_snake_should_be_drawn_inside_its_border() {
+    let snake = Snake::new(5, 3, Direction::East, 0, new_rng(1));
+    let mut buffer = Buffer::new(7, 5);
+
+    snake.print(&mut buffer, 1, 1);
+
+    assert_eq!(vec!("", "", "   #", "", ""), buffer.lines());
+    assert_eq!(Color::Border, buffer.cell(1, 3).bg);
+    assert_eq!(Color::Border, buffer.cell(7, 5).bg);
+}
@@ -1,98 +1,206 @@
-use std::{io, thread};
-use std::io::{Error, Read, Write};
+use std::io;
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::time::Duration;
 
 use termion::event::Key;
-use termion::input::TermRead;
 
-use crate::common::persistence::HighScores;
+use crate::common::canvas::Canvas;
+use crate::common::consolecolor::Color;
+use crate::common::difficulty::Difficulty;
+use crate::common::gameloop::{Flow, Game, GameLoop};
+use crate::common::gamemain::GameMain;
+use crate::common::help::Help;
+use crate::common::keys::{Action, KeyBindings};
+use crate::common::options::GameOptions;
+use crate::common::persistence::Scoring;
 use crate::common::point::Direction;
-use crate::Main;
-use crate::snake::snake::Snake;
-
+use crate::common::random::GameRng;
+use crate::common::result::{Counter, GameResult, Outcome};
+use crate::common::screen::Screen;
+use crate::snake::bot;
+use crate::snake::snake::{FOOD_SCORE, Snake};
+
+const NAME: &str = "Snake";
+const DIR: &str = "snake";
 const FOOD: u8 = 10;
 const WIDTH: u8 = 20;
 const HEIGHT: u8 = 20;
+/// How long the snake takes to move a cell, at Easy, Normal and Hard.
+const TICKS: [Duration; 3] = [Duration::from_millis(250), Duration::from_millis(200), Duration::from_millis(130)];
 
 pub struct SnakeMain<W: Write> {
+    options: GameOptions,
     _marker: PhantomData<W>,
 }
 
 impl<W: Write> SnakeMain<W> {
-    pub fn new() -> SnakeMain<W> {
-        SnakeMain { _marker: PhantomData }
+    pub fn new(options: GameOptions) -> SnakeMain<W> {
+        SnakeMain { options, _marker: PhantomData }
     }
 }
 
-impl<W: Write, R: Read> Main<W, R> for SnakeMain<W> {
+impl<W: Write, R: Read> GameMain<W, R> for SnakeMain<W> {
+    type Game = SnakeGame;
+    type Saved = Snake;
+
     fn name(&self) -> &'static str {
-        "Snake"
-    }
-
-    fn run(&self, mut stdout: &mut W, stdin: &mut R) -> io::Result<Option<u32>> {
-        let mut snake = Snake::new(WIDTH, HEIGHT, Direction::East, FOOD);
-
-        let mut result: io::Result<Option<u32>> = Result::Ok(None);
-
-        'outer: loop {
-            for _i in 0..20 {
-                let mut key_pressed = false;
-
-                if let Some(key_or_error) = stdin.keys().next() {
-                    let key = key_or_error?;
-
-                    if let Key::Esc = key {
-                        break 'outer;
-                    } else if let Key::Left = key {
-                        snake = snake.mv(Direction::West);
-                        key_pressed = true;
-                    } else if let Key::Right = key {
-                        snake = snake.mv(Direction::East);
-                        key_pressed = true;
-                    } else if let Key::Up = key {
-                        snake = snake.mv(Direction::North);
-                        key_pressed = true;
-                    } else if let Key::Down = key {
-                        snake = snake.mv(Direction::South);
-                        key_pressed = true;
-                    }
-                }
-
-                if key_pressed {
-                    print(&mut stdout, &snake)?;
-                }
-
-                thread::sleep(Duration::from_millis(10));
-            }
-
-            if let Some(next_snake) = snake.next() {
-                snake = next_snake;
-                print(&mut stdout, &snake)?;
-            } else {
-                result = Result::Ok(Some(snake.score()));
-                break 'outer;
-            }
+        NAME
+    }
+
+    fn dir(&self) -> &'static str {
+        DIR
+    }
+
+    fn options(&self) -> &GameOptions {
+        &self.options
+    }
+
+    fn scoring(&self) -> Scoring {
+        Scoring::POINTS
+    }
+
+    fn bindings(&self) -> io::Result<KeyBindings> {
+        KeyBindings::load(NAME, default_bindings())
+    }
+
+    fn explain(&self, bindings: &KeyBindings) -> Help {
+        explain(bindings)
+    }
+
+    fn size(&self) -> (u8, u8) {
+        self.options.size((WIDTH, HEIGHT), (5, 5))
+    }
+
+    fn tick(&self) -> Duration {
+        let difficulty = self.options.difficulty;
+        difficulty.preset(TICKS).div_f32(difficulty.speed())
+    }
+
+    fn new_game(&self, width: u8, height: u8, _: &KeyBindings, rng: GameRng) -> SnakeGame {
+        SnakeGame { snake: Snake::new(width, height, Direction::East, FOOD, rng) }
+    }
+
+    fn continue_game(&self, snake: Snake, _: &KeyBindings) -> SnakeGame {
+        SnakeGame { snake }
+    }
+
+    fn saved(&self, game: SnakeGame) -> Snake {
+        game.snake
+    }
+
+    fn game_over(&self, game: &SnakeGame, duration: Duration) -> GameResult {
+        let mode = format!("{}x{}", game.snake.width(), game.snake.height());
+        let stats = vec!(("length", game.snake.length()), ("food", game.snake.eaten()));
+        let counters = vec!(Counter::Total("food eaten", game.snake.eaten()));
+        let score = Some(game.snake.score());
+        GameResult::new(Outcome::Lost, score, duration, mode, stats).counting(counters)
+    }
+
+    fn demo(&self, stdout: &mut W, stdin: &mut R, length: Duration) -> io::Result<bool> {
+        let (width, height) = self.options.size((WIDTH, HEIGHT), (5, 5));
+        let bindings = KeyBindings::new(default_bindings());
+
+        let game_loop = GameLoop::new(Difficulty::Normal.preset(TICKS), explain(&bindings), bindings).demoing(length);
+
+        let (flow, _) = game_loop.play(stdout, stdin, None, None, |rng|
+            SnakeGame { snake: Snake::new(width, height, Direction::East, FOOD, rng) })?;
+
+        Ok(flow != Flow::Quit)
+    }
+}
+
+fn explain(bindings: &KeyBindings) -> Help {
+    Help::new("Eat the food without running into the walls or into yourself: the snake grows with every bite.",
+              &format!("{} points for every food eaten.", FOOD_SCORE), bindings)
+}
+
+fn default_bindings() -> Vec<(Action, Vec<Key>)> {
+    vec!(
+        (Action::MoveLeft, vec!(Key::Left)),
+        (Action::MoveRight, vec!(Key::Right)),
+        (Action::MoveUp, vec!(Key::Up)),
+        (Action::MoveDown, vec!(Key::Down))
+    )
+}
+
+pub struct SnakeGame {
+    snake: Snake,
+}
+
+impl Game for SnakeGame {
+    fn action(&mut self, action: Action) -> io::Result<Flow> {
+        match action {
+            Action::MoveLeft => self.snake = self.snake.mv(Direction::West),
+            Action::MoveRight => self.snake = self.snake.mv(Direction::East),
+            Action::MoveUp => self.snake = self.snake.mv(Direction::North),
+            Action::MoveDown => self.snake = self.snake.mv(Direction::South),
+            _ => {}
         }
+        Ok(Flow::Continue)
+    }
+
+    fn tick(&mut self) -> io::Result<Flow> {
+        if let Some(snake) = self.snake.next() {
+            self.snake = snake;
+            Ok(Flow::Continue)
+        } else {
+            Ok(Flow::GameOver)
+        }
+    }
 
-        while stdin.keys().next().is_some() {}
+    fn render(&self, screen: &mut Screen) {
+        print(screen, &self.snake)
+    }
 
-        result
+    fn bot(&self) -> Vec<Action> {
+        bot::actions(&self.snake)
     }
 
-    fn high_scores(&self) -> Result<HighScores, Error> {
-        HighScores::read(".snake")
+    fn size(&self) -> (u16, u16) {
+        (self.snake.width() as u16 + 2, self.snake.height() as u16 + 3)
     }
 }
 
-fn print<W: Write>(mut stdout: &mut W, snake: &Snake) -> io::Result<()> {
-    write!(stdout,
-           "{}{}Score: {}",
-           termion::clear::All,
-           termion::cursor::Goto(1, 1),
-           snake.score())?;
+fn print(screen: &mut Screen, snake: &Snake) {
+    screen.text(1, 1, &format!("Score: {}", snake.score()), Color::DefaultColor, Color::DefaultColor);
+    snake.print(screen, 1, 2);
+}
+
+#[cfg(test)]
+use crate::common::headless::{assert_snapshot, DOWN, Headless, LEFT, play, Script, UP};
+
+#[test]
+fn snake_should_be_played_until_it_hits_the_wall() {
+    let options = GameOptions { width: Some(10), height: Some(6), seed: Some(1), ..GameOptions::default() };
+    let script = Script::new()
+        .keys(&[UP]).wait(400)
+        .keys(&[LEFT]).wait(800)
+        .keys(&[DOWN]);
+
+    let (score, terminal) = play(&SnakeMain::new(options), script, 20, 10);
+
+    let result = score.unwrap();
+    assert_eq!(Outcome::Lost, result.outcome);
+    assert_eq!(Some(300), result.score);
+    assert_eq!("10x6", result.mode);
+    assert!(result.stats.contains(&("food", 3)));
+    assert_snapshot("snake_game_over", &terminal.snapshot());
+}
+
+#[test]
+fn the_demo_should_be_played_by_the_bot_until_a_key_is_pressed() {
+    let headless = Headless::new(40, 24);
+    let main = SnakeMain::new(GameOptions::default());
 
-    snake.print(&mut stdout, 1, 2)?;
+    let (shown, terminal) = headless.run(Script::new().wait(1000).keys(&["x"]), |terminal, script|
+        main.demo(terminal, script, Duration::from_secs(20)));
 
-    stdout.flush()
-}
\ No newline at end of file
+    assert!(!shown.unwrap());
+    assert!(terminal.snapshot().contains('#'));
+
+    let (shown, _) = headless.run(Script::new(), |terminal, script| main.demo(terminal, script, Duration::from_secs(1)));
+
+    assert!(shown.unwrap());
+    assert!(!headless.home().join("data").join(DIR).exists());
+}
@@ -0,0 +1,68 @@
+use crate::common::keys::Action;
+use crate::common::point::Pointf32;
+use crate::spaceinvaders::spaceinvaders::SpaceInvaders;
+
+/// Rows above the cannon where a bullet of the invaders is dodged.
+const DANGER: f32 = 4.0;
+/// Bullets of the cannon flying at once.
+const MAX_BULLETS: usize = 3;
+
+/// Dodges the bullets falling on the cannon, shoots when an invader is above it, and otherwise goes under the
+/// nearest one.
+pub fn actions(spaceinvaders: &SpaceInvaders) -> Vec<Action> {
+    let x = spaceinvaders.x();
+    let bottom = spaceinvaders.height() as f32;
+
+    let threatened = |x: i16| spaceinvaders.enemy_bullets().iter()
+        .any(|bullet| bullet.x as i16 == x && bullet.y >= bottom - DANGER);
+
+    if threatened(x as i16) {
+        return if x > 0 && !threatened(x as i16 - 1) {
+            vec!(Action::MoveLeft)
+        } else {
+            vec!(Action::MoveRight)
+        };
+    }
+
+    if spaceinvaders.bullets().len() < MAX_BULLETS && spaceinvaders.enemies().iter().any(|enemy| enemy.x as u8 == x) {
+        return vec!(Action::Fire);
+    }
+
+    match nearest(spaceinvaders.enemies(), x) {
+        Some(target) if target < x && !threatened(x as i16 - 1) => vec!(Action::MoveLeft),
+        Some(target) if target > x && !threatened(x as i16 + 1) => vec!(Action::MoveRight),
+        _ => Vec::new()
+    }
+}
+
+/// The column of the invader nearest to the cannon's.
+fn nearest(enemies: &[Pointf32], x: u8) -> Option<u8> {
+    enemies.iter()
+        .map(|enemy| enemy.x as u8)
+        .min_by_key(|&column| (column as i16 - x as i16).abs())
+}
+
+#[cfg(test)]
+use crate::common::random::new_rng;
+
+#[test]
+fn the_bot_should_shoot_invaders() {
+    let mut spaceinvaders = SpaceInvaders::new(40, 20, 0.1, 1.005, new_rng(1));
+
+    for _ in 0..1000 {
+        for action in actions(&spaceinvaders) {
+            spaceinvaders = match action {
+                Action::MoveLeft => spaceinvaders.left(),
+                Action::MoveRight => spaceinvaders.right(),
+                _ => spaceinvaders.fire()
+            };
+        }
+
+        match spaceinvaders.next() {
+            Some(next) if !next.cleared() => spaceinvaders = next,
+            _ => break
+        }
+    }
+
+    assert!(spaceinvaders.killed() >= 10, "{} killed", spaceinvaders.killed());
+}
@@ -1,2 +1,4 @@
+mod bot;
+#[allow(clippy::module_inception)]
 mod spaceinvaders;
 pub mod spaceinvadersmain;
\ No newline at end of file
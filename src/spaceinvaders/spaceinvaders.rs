@@ -1,40 +1,48 @@
-use std::io;
-use std::io::Write;
-
 use rand::Rng;
-use termion::color;
 
+use crate::common::canvas::Canvas;
+use crate::common::consolecolor::Color;
 use crate::common::point::Pointf32;
+use crate::common::random::GameRng;
 
-const WIDTH: u8 = 40;
-const HEIGHT: u8 = 20;
 const MARGIN: u8 = 2;
+pub const INVADER_SCORE: u32 = 1000;
 
+#[derive(Serialize, Deserialize)]
 pub struct SpaceInvaders {
+    width: u8,
+    height: u8,
     x: u8,
     enemies: Vec<Pointf32>,
     bullets: Vec<Pointf32>,
     enemy_bullets: Vec<Pointf32>,
     enemy_velocity: f32,
+    /// How much faster the invaders get on every tick.
+    acceleration: f32,
     score: u32,
+    rng: GameRng,
 }
 
 impl SpaceInvaders {
-    pub fn new() -> SpaceInvaders {
+    pub fn new(width: u8, height: u8, velocity: f32, acceleration: f32, rng: GameRng) -> SpaceInvaders {
         let mut enemies: Vec<Pointf32> = Vec::new();
-        for x in (3 * MARGIN..(WIDTH - 3 * MARGIN)).step_by(3) {
+        for x in (3 * MARGIN..(width - 3 * MARGIN)).step_by(3) {
             for y in (0..8).step_by(2) {
                 enemies.push(Pointf32::new(x as f32, y as f32));
             }
         }
 
         SpaceInvaders {
-            x: WIDTH / 2,
+            width,
+            height,
+            x: width / 2,
             enemies,
             bullets: Vec::new(),
             enemy_bullets: Vec::new(),
-            enemy_velocity: 0.1,
+            enemy_velocity: velocity,
+            acceleration,
             score: 0,
+            rng,
         }
     }
 
@@ -48,19 +56,31 @@ impl SpaceInvaders {
         let enemies: Vec<Pointf32> = self.enemies.iter()
             .filter(|enemy| self.bullets.iter()
                 .all(|bullet| !SpaceInvaders::collides(bullet, enemy)))
-            .map(|point| point.clone()).collect();
+            .cloned().collect();
+
+        // the last invaders are shot down
+        if enemies.is_empty() {
+            return Some(SpaceInvaders {
+                width: self.width,
+                height: self.height,
+                x: self.x,
+                enemies,
+                bullets: Vec::new(),
+                enemy_bullets: Vec::new(),
+                enemy_velocity: self.enemy_velocity,
+                acceleration: self.acceleration,
+                score: self.score + INVADER_SCORE * self.enemies.len() as u32,
+                rng: self.rng.clone(),
+            });
+        }
 
         let max_x_o = enemies.iter().map(|point| point.x as u8).max();
         let min_x_o = enemies.iter().map(|point| point.x as u8).min();
         let max_y_o = enemies.iter().map(|point| point.y as u8).max();
 
-        if max_x_o.is_none() {
-            return None;
-        }
-
-        let max_x = max_x_o.unwrap() as u8;
-        let min_x = min_x_o.unwrap() as u8;
-        let max_y = max_y_o.unwrap() as u8;
+        let max_x = max_x_o?;
+        let min_x = min_x_o.unwrap();
+        let max_y = max_y_o.unwrap();
 
         let bullets: Vec<Pointf32> = self.bullets.iter()
             .filter(|bullet|
@@ -72,12 +92,14 @@ impl SpaceInvaders {
         let mut enemy_direction = self.enemy_velocity;
 
         let mut enemy_bullets: Vec<Pointf32> = self.enemy_bullets.iter().map(|point| point.down())
-            .filter(|point| (point.y as u8) <= HEIGHT).collect();
+            .filter(|point| (point.y as u8) <= self.height).collect();
+
+        let mut rng = self.rng.clone();
 
-        let enemies: Vec<Pointf32> = if max_x >= WIDTH - MARGIN || min_x <= MARGIN {
+        let enemies: Vec<Pointf32> = if max_x >= self.width - MARGIN || min_x <= MARGIN {
             enemy_direction = -enemy_direction;
 
-            enemy_bullets.push(SpaceInvaders::enemy_fire(&enemies, max_y));
+            enemy_bullets.push(SpaceInvaders::enemy_fire(&enemies, max_y, &mut rng));
 
             enemies.iter().map(|point| Pointf32::new(point.x + enemy_direction, point.y + 1.0))
                 .collect()
@@ -86,41 +108,49 @@ impl SpaceInvaders {
                 .collect()
         };
 
-        if enemies.iter().any(|enemy| enemy.y >= HEIGHT as f32) {
+        if enemies.iter().any(|enemy| enemy.y >= self.height as f32) {
             return None;
         }
 
-        let position = Pointf32::new(self.x as f32, HEIGHT as f32);
+        let position = Pointf32::new(self.x as f32, self.height as f32);
 
         if enemy_bullets.iter().any(|bullet| SpaceInvaders::collides(bullet, &position)) {
             return None;
         }
 
-        let score = self.score + (1000 * (self.enemies.len() - enemies.len())) as u32;
+        let score = self.score + INVADER_SCORE * (self.enemies.len() - enemies.len()) as u32;
 
         Some(SpaceInvaders {
+            width: self.width,
+            height: self.height,
             x: self.x,
             enemies,
             bullets,
             enemy_bullets,
-            enemy_velocity: enemy_direction * 1.005,
+            enemy_velocity: enemy_direction * self.acceleration,
+            acceleration: self.acceleration,
             score,
+            rng,
         })
     }
 
     pub fn right(&self) -> SpaceInvaders {
         let mut x = self.x + 1;
 
-        if x > WIDTH {
+        if x > self.width {
             x = self.x;
         }
         SpaceInvaders {
+            width: self.width,
+            height: self.height,
             x,
             enemies: self.enemies.clone(),
             bullets: self.bullets.clone(),
             enemy_bullets: self.enemy_bullets.clone(),
             enemy_velocity: self.enemy_velocity,
+            acceleration: self.acceleration,
             score: self.score,
+            rng: self.rng.clone(),
         }
     }
 
@@ -130,72 +160,101 @@ impl SpaceInvaders {
             x = self.x as i8;
         }
         SpaceInvaders {
+            width: self.width,
+            height: self.height,
             x: x as u8,
             enemies: self.enemies.clone(),
             bullets: self.bullets.clone(),
             enemy_bullets: self.enemy_bullets.clone(),
             enemy_velocity: self.enemy_velocity,
+            acceleration: self.acceleration,
             score: self.score,
+            rng: self.rng.clone(),
         }
     }
 
     pub fn fire(&self) -> SpaceInvaders {
         let mut bullets = self.bullets.clone();
 
-        bullets.push(Pointf32::new(self.x as f32, HEIGHT as f32 - 1.0));
+        bullets.push(Pointf32::new(self.x as f32, self.height as f32 - 1.0));
 
         SpaceInvaders {
+            width: self.width,
+            height: self.height,
             x: self.x,
             enemies: self.enemies.clone(),
             bullets,
             enemy_bullets: self.enemy_bullets.clone(),
             enemy_velocity: self.enemy_velocity,
+            acceleration: self.acceleration,
             score: self.score,
+            rng: self.rng.clone(),
         }
     }
 
-    pub fn print<W: Write>(&self, term: &mut W, x: u16, y: u16) -> io::Result<()> {
-        write!(term, "{}",
-               termion::style::Reset)?;
-
+    pub fn print<C: Canvas>(&self, canvas: &mut C, x: u16, y: u16) {
         for enemy in self.enemies.iter() {
-            write!(term, "{}M",
-                   termion::cursor::Goto(enemy.x as u16 + x + 1, enemy.y as u16 + y + 1))?;
+            canvas.put(enemy.x as u16 + x + 1, enemy.y as u16 + y + 1, 'M', Color::DefaultColor, Color::DefaultColor);
         }
 
         for bullet in self.bullets.iter() {
-            write!(term, "{}|",
-                   termion::cursor::Goto(bullet.x as u16 + x + 1, bullet.y as u16 + y + 1))?;
+            canvas.put(bullet.x as u16 + x + 1, bullet.y as u16 + y + 1, '|', Color::DefaultColor, Color::DefaultColor);
         }
 
-        write!(term, "{}",
-               color::Fg(color::Red))?;
-
         for bullet in self.enemy_bullets.iter() {
-            write!(term, "{}|",
-                   termion::cursor::Goto(bullet.x as u16 + x + 1, bullet.y as u16 + y + 1))?;
+            canvas.put(bullet.x as u16 + x + 1, bullet.y as u16 + y + 1, '|', Color::Red, Color::DefaultColor);
         }
 
-        write!(term, "{}",
-               termion::style::Reset)?;
-
-        write!(term, "{}A",
-               termion::cursor::Goto(self.x as u16 + x + 1, HEIGHT as u16 + y + 1))
+        canvas.put(self.x as u16 + x + 1, self.height as u16 + y + 1, 'A', Color::DefaultColor, Color::DefaultColor);
     }
 
     pub fn score(&self) -> u32 {
         self.score
     }
 
+    /// Whether every invader was shot down.
+    pub fn cleared(&self) -> bool {
+        self.enemies.is_empty()
+    }
+
+    /// Invaders shot so far.
+    pub fn killed(&self) -> u32 {
+        self.score / INVADER_SCORE
+    }
+
+    /// Where the cannon is, on the bottom row.
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+
+    pub fn enemies(&self) -> &[Pointf32] {
+        &self.enemies
+    }
+
+    pub fn bullets(&self) -> &[Pointf32] {
+        &self.bullets
+    }
+
+    pub fn enemy_bullets(&self) -> &[Pointf32] {
+        &self.enemy_bullets
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
     fn collides(bullet: &Pointf32, enemy: &Pointf32) -> bool {
         bullet.x as u16 == enemy.x as u16 && bullet.y as u16 == enemy.y as u16
     }
 
-    fn enemy_fire(enemies: &Vec<Pointf32>, max_y: u8) -> Pointf32 {
+    fn enemy_fire(enemies: &[Pointf32], max_y: u8, rng: &mut GameRng) -> Pointf32 {
         let enemies_to_fire: Vec<Pointf32> = enemies.iter().filter(|point| point.y as u8 == max_y)
-            .map(|point| point.clone()).collect();
+            .cloned().collect();
 
-        let mut rng = rand::thread_rng();
         let index = rng.gen_range(0, enemies_to_fire.len());
 
         let bullet_x = enemies_to_fire[index].x;
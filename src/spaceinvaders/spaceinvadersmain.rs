@@ -1,96 +1,190 @@
-use std::{io, thread};
-use std::io::{Error, Read, Write};
+use std::io;
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::time::Duration;
 
 use termion::event::Key;
-use termion::input::TermRead;
 
-use crate::common::persistence::HighScores;
-use crate::Main;
-use crate::spaceinvaders::spaceinvaders::SpaceInvaders;
+use crate::common::canvas::Canvas;
+use crate::common::consolecolor::Color;
+use crate::common::difficulty::Difficulty;
+use crate::common::gameloop::{Flow, Game, GameLoop};
+use crate::common::gamemain::GameMain;
+use crate::common::help::Help;
+use crate::common::keys::{Action, KeyBindings};
+use crate::common::options::GameOptions;
+use crate::common::persistence::Scoring;
+use crate::common::random::GameRng;
+use crate::common::result::{Counter, GameResult, Outcome};
+use crate::common::screen::Screen;
+use crate::spaceinvaders::bot;
+use crate::spaceinvaders::spaceinvaders::{INVADER_SCORE, SpaceInvaders};
+
+const NAME: &str = "Space Invaders";
+const DIR: &str = "spaceinvaders";
+const WIDTH: u8 = 40;
+const HEIGHT: u8 = 20;
+const TICK: Duration = Duration::from_millis(100);
+/// How far the invaders move on every tick at first, at Easy, Normal and Hard.
+const VELOCITIES: [f32; 3] = [0.07, 0.1, 0.13];
+/// How much faster the invaders get on every tick.
+const ACCELERATIONS: [f32; 3] = [1.003, 1.005, 1.008];
 
 pub struct SpaceInvadersMain<W: Write> {
+    options: GameOptions,
     _marker: PhantomData<W>,
 }
 
 impl<W: Write> SpaceInvadersMain<W> {
-    pub fn new() -> SpaceInvadersMain<W> {
-        SpaceInvadersMain { _marker: PhantomData }
+    pub fn new(options: GameOptions) -> SpaceInvadersMain<W> {
+        SpaceInvadersMain { options, _marker: PhantomData }
     }
 }
 
-impl<W: Write, R: Read> Main<W, R> for SpaceInvadersMain<W> {
+impl<W: Write, R: Read> GameMain<W, R> for SpaceInvadersMain<W> {
+    type Game = SpaceInvadersGame;
+    type Saved = SpaceInvaders;
+
     fn name(&self) -> &'static str {
-        "Space Invaders"
+        NAME
     }
 
-    fn run(&self, mut stdout: &mut W, stdin: &mut R) -> io::Result<Option<u32>> {
-        write!(stdout,
-               "{}",
-               termion::clear::All)?;
-        stdout.flush()?;
+    fn dir(&self) -> &'static str {
+        DIR
+    }
 
-        let mut spaceinvaders = SpaceInvaders::new();
+    fn options(&self) -> &GameOptions {
+        &self.options
+    }
 
-        let mut result: io::Result<Option<u32>> = Result::Ok(None);
+    fn scoring(&self) -> Scoring {
+        Scoring::POINTS
+    }
 
-        'outer: loop {
-            for _i in 0..20 {
-                let mut key_pressed = false;
+    fn bindings(&self) -> io::Result<KeyBindings> {
+        KeyBindings::load(NAME, default_bindings())
+    }
 
-                if let Some(key_or_error) = stdin.keys().next() {
-                    let key = key_or_error?;
+    fn explain(&self, bindings: &KeyBindings) -> Help {
+        explain(bindings)
+    }
 
-                    if let Key::Esc = key {
-                        break 'outer;
-                    } else if let Key::Left = key {
-                        spaceinvaders = spaceinvaders.left();
-                        key_pressed = true;
-                    } else if let Key::Right = key {
-                        spaceinvaders = spaceinvaders.right();
-                        key_pressed = true;
-                    } else if let Key::Char(' ') = key {
-                        spaceinvaders = spaceinvaders.fire();
-                        key_pressed = true;
-                    }
-                }
+    fn size(&self) -> (u8, u8) {
+        self.options.size((WIDTH, HEIGHT), (20, 12))
+    }
 
-                if key_pressed {
-                    print(&mut stdout, &spaceinvaders)?;
-                }
+    /// The same at every difficulty: the invaders move farther on each tick.
+    fn tick(&self) -> Duration {
+        TICK
+    }
 
-                thread::sleep(Duration::from_millis(5));
-            }
+    fn new_game(&self, width: u8, height: u8, _: &KeyBindings, rng: GameRng) -> SpaceInvadersGame {
+        let difficulty = self.options.difficulty;
+        let velocity = difficulty.preset(VELOCITIES) * difficulty.speed();
+        let acceleration = difficulty.preset(ACCELERATIONS);
+        SpaceInvadersGame { spaceinvaders: SpaceInvaders::new(width, height, velocity, acceleration, rng) }
+    }
+
+    fn continue_game(&self, spaceinvaders: SpaceInvaders, _: &KeyBindings) -> SpaceInvadersGame {
+        SpaceInvadersGame { spaceinvaders }
+    }
+
+    fn saved(&self, game: SpaceInvadersGame) -> SpaceInvaders {
+        game.spaceinvaders
+    }
+
+    fn game_over(&self, game: &SpaceInvadersGame, duration: Duration) -> GameResult {
+        let mode = format!("{}x{}", game.spaceinvaders.width(), game.spaceinvaders.height());
+        let stats = vec!(("invaders", game.spaceinvaders.killed()));
+        let outcome = if game.spaceinvaders.cleared() { Outcome::Cleared } else { Outcome::Lost };
+        let counters = vec!(Counter::Total("invaders killed", game.spaceinvaders.killed()));
+        let score = Some(game.spaceinvaders.score());
+        GameResult::new(outcome, score, duration, mode, stats).counting(counters)
+    }
+
+    fn demo(&self, stdout: &mut W, stdin: &mut R, length: Duration) -> io::Result<bool> {
+        let (width, height) = self.options.size((WIDTH, HEIGHT), (20, 12));
+        let bindings = KeyBindings::new(default_bindings());
+        let (velocity, acceleration) = (Difficulty::Normal.preset(VELOCITIES), Difficulty::Normal.preset(ACCELERATIONS));
+
+        let game_loop = GameLoop::new(TICK, explain(&bindings), bindings).demoing(length);
 
-            if let Some(next_spaceinvaders) = spaceinvaders.next() {
-                spaceinvaders = next_spaceinvaders;
+        let (flow, _) = game_loop.play(stdout, stdin, None, None, |rng|
+            SpaceInvadersGame { spaceinvaders: SpaceInvaders::new(width, height, velocity, acceleration, rng) })?;
 
-                print(&mut stdout, &spaceinvaders)?;
-            } else {
-                result = Result::Ok(Some(spaceinvaders.score()));
-                break 'outer;
+        Ok(flow != Flow::Quit)
+    }
+}
+
+fn explain(bindings: &KeyBindings) -> Help {
+    Help::new("Shoot down all the invaders before they land, and dodge their bullets.",
+              &format!("{} points for every invader shot down.", INVADER_SCORE), bindings)
+}
+
+fn default_bindings() -> Vec<(Action, Vec<Key>)> {
+    vec!(
+        (Action::MoveLeft, vec!(Key::Left)),
+        (Action::MoveRight, vec!(Key::Right)),
+        (Action::Fire, vec!(Key::Char(' ')))
+    )
+}
+
+pub struct SpaceInvadersGame {
+    spaceinvaders: SpaceInvaders,
+}
+
+impl Game for SpaceInvadersGame {
+    fn action(&mut self, action: Action) -> io::Result<Flow> {
+        match action {
+            Action::MoveLeft => self.spaceinvaders = self.spaceinvaders.left(),
+            Action::MoveRight => self.spaceinvaders = self.spaceinvaders.right(),
+            Action::Fire => self.spaceinvaders = self.spaceinvaders.fire(),
+            _ => {}
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn tick(&mut self) -> io::Result<Flow> {
+        match self.spaceinvaders.next() {
+            Some(spaceinvaders) => {
+                self.spaceinvaders = spaceinvaders;
+                Ok(if self.spaceinvaders.cleared() { Flow::GameOver } else { Flow::Continue })
             }
+            None => Ok(Flow::GameOver)
         }
+    }
 
-        while stdin.keys().next().is_some() {}
+    fn render(&self, screen: &mut Screen) {
+        print(screen, &self.spaceinvaders)
+    }
 
-        result
+    fn bot(&self) -> Vec<Action> {
+        bot::actions(&self.spaceinvaders)
     }
 
-    fn high_scores(&self) -> Result<HighScores, Error> {
-        HighScores::read(".spaceinvaders")
+    fn size(&self) -> (u16, u16) {
+        (self.spaceinvaders.width() as u16 + 2, self.spaceinvaders.height() as u16 + 3)
     }
 }
 
-fn print<W: Write>(mut stdout: &mut W, spaceinvaders: &SpaceInvaders) -> io::Result<()> {
-    write!(stdout,
-           "{}{}Score: {}",
-           termion::clear::All,
-           termion::cursor::Goto(1, 1),
-           spaceinvaders.score())?;
+fn print(screen: &mut Screen, spaceinvaders: &SpaceInvaders) {
+    screen.text(1, 1, &format!("Score: {}", spaceinvaders.score()), Color::DefaultColor, Color::DefaultColor);
+    spaceinvaders.print(screen, 1, 2);
+}
+
+#[cfg(test)]
+use crate::common::headless::{assert_snapshot, LEFT, play, Script};
 
-    spaceinvaders.print(&mut stdout, 1, 2)?;
+#[test]
+fn space_invaders_should_be_played_until_the_invaders_land() {
+    let options = GameOptions { width: Some(20), height: Some(12), seed: Some(1), ..GameOptions::default() };
+    let script = Script::new()
+        .keys(&[" "]).wait(500)
+        .keys(&[LEFT, LEFT, " "]).wait(500)
+        .keys(&[LEFT, LEFT, " "]);
 
-    stdout.flush()
-}
\ No newline at end of file
+    let (score, terminal) = play(&SpaceInvadersMain::new(options), script, 30, 18);
+
+    assert_eq!(Some(1000), score.unwrap().score);
+    assert_snapshot("spaceinvaders_game_over", &terminal.snapshot());
+}
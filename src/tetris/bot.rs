@@ -0,0 +1,91 @@
+use std::io;
+
+use crate::common::grid::Grid;
+use crate::common::keys::Action;
+use crate::tetris::tetris::Tetris;
+
+/// Weights of what a placement leaves, from the well known heuristic by Yiyuan Lee.
+const HEIGHT_WEIGHT: f32 = -0.51;
+const LINES_WEIGHT: f32 = 0.76;
+const HOLES_WEIGHT: f32 = -0.36;
+const BUMPINESS_WEIGHT: f32 = -0.18;
+
+/// Rotates and moves the falling shape to where it leaves the best grid, then drops it.
+pub fn actions(tetris: &Tetris) -> Vec<Action> {
+    placement(tetris).unwrap_or_default()
+}
+
+fn placement(tetris: &Tetris) -> io::Result<Vec<Action>> {
+    if !tetris.falling() {
+        return Ok(Vec::new());
+    }
+
+    let mut best: Option<(f32, Vec<Action>)> = None;
+    let mut rotated = tetris.clone();
+
+    for rotations in 0..4 {
+        // a move into the wall leaves the shape where it is, so moving as far as the width tries every column
+        for &(action, first) in &[(Action::MoveLeft, 0), (Action::MoveRight, 1)] {
+            for moves in first..=tetris.width() {
+                let mut moved = rotated.clone();
+
+                for _ in 0..moves {
+                    moved = if action == Action::MoveLeft { moved.left()? } else { moved.right()? };
+                }
+
+                let dropped = moved.fall()?;
+                let value = evaluate(dropped.grid(), dropped.lines() - tetris.lines());
+
+                if best.as_ref().map(|(best, _)| value > *best).unwrap_or(true) {
+                    let mut actions = vec!(Action::RotateCW; rotations);
+                    actions.extend(vec!(action; moves as usize));
+                    actions.push(Action::HardDrop);
+                    best = Some((value, actions));
+                }
+            }
+        }
+
+        rotated = rotated.rotate_right()?;
+    }
+
+    Ok(best.map(|(_, actions)| actions).unwrap_or_default())
+}
+
+/// Higher is better: few and low columns of even heights, without holes under them, and lines cleared.
+fn evaluate(grid: &Grid, lines: u32) -> f32 {
+    let heights: Vec<u32> = (0..grid.width)
+        .map(|x| (0..grid.height).find(|&y| grid.occupied(x, y)).map(|y| (grid.height - y) as u32).unwrap_or(0))
+        .collect();
+
+    let holes: u32 = (0..grid.width)
+        .map(|x| (grid.height - heights[x as usize] as u8..grid.height).filter(|&y| !grid.occupied(x, y)).count() as u32)
+        .sum();
+
+    let bumpiness: u32 = heights.windows(2).map(|pair| (pair[0] as i32 - pair[1] as i32).unsigned_abs()).sum();
+
+    HEIGHT_WEIGHT * heights.iter().sum::<u32>() as f32 + LINES_WEIGHT * lines as f32 + HOLES_WEIGHT * holes as f32
+        + BUMPINESS_WEIGHT * bumpiness as f32
+}
+
+#[cfg(test)]
+use crate::common::random::new_rng;
+
+#[test]
+fn the_bot_should_clear_lines() {
+    let mut tetris = Tetris::new(10, 20, new_rng(1));
+
+    for _ in 0..60 {
+        for action in actions(&tetris) {
+            tetris = match action {
+                Action::RotateCW => tetris.rotate_right().unwrap(),
+                Action::MoveLeft => tetris.left().unwrap(),
+                Action::MoveRight => tetris.right().unwrap(),
+                _ => tetris.fall().unwrap()
+            };
+        }
+
+        tetris = tetris.next().unwrap().expect("the bot should not lose so soon");
+    }
+
+    assert!(tetris.lines() >= 15, "{} lines", tetris.lines());
+}
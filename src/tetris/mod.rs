@@ -1,3 +1,5 @@
+mod bot;
 mod shape;
+#[allow(clippy::module_inception)]
 mod tetris;
 pub mod tetrismain;
\ No newline at end of file
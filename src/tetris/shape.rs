@@ -4,7 +4,7 @@ use crate::common::consolecolor::Color;
 use crate::common::grid::Grid;
 use crate::common::point::Point;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Shape {
     points: Vec<Point>,
     pub color: Color,
@@ -71,13 +71,13 @@ impl Shape {
     }
 
     pub fn print(&self, grid: Grid, x: u8, y: u8) -> Grid {
-        self.points.to_vec().into_iter()
+        self.points.iter()
             .fold(grid, |prev, point| prev.set((x as i8 + point.x) as u8, (y as i8 + point.y) as u8,
-                                               self.color.clone()))
+                                               self.color))
     }
 
     pub fn clear(&self, grid: Grid, x: u8, y: u8) -> Grid {
-        self.points.to_vec().into_iter()
+        self.points.iter()
             .fold(grid.clone(), |prev, point| prev.set((x as i8 + point.x) as u8, (y as i8 + point.y) as u8,
                                                        Color::DefaultColor))
     }
@@ -91,17 +91,17 @@ impl Shape {
     }
 
     pub fn to_points(&self, x: i8, y: i8) -> Vec<Point> {
-        self.points.to_vec().into_iter()
+        self.points.iter()
             .map(|point| Point { x: x + point.x, y: y + point.y }).collect()
     }
 
     fn rotate_by_angle(&self, ang: f32) -> Shape {
-        let points = self.points.to_vec().into_iter().map(|point| {
+        let points = self.points.iter().map(|point| {
             let distance = ((point.x * point.x + point.y * point.y) as f32).sqrt();
             let angle = (point.y as f32).atan2(point.x as f32) + ang;
             Point { x: (distance * angle.cos()).round() as i8, y: (distance * angle.sin()).round() as i8 }
         }).collect::<Vec<_>>();
 
-        Shape { points, color: self.color.clone() }
+        Shape { points, color: self.color }
     }
 }
\ No newline at end of file
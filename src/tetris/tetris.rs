@@ -1,19 +1,20 @@
 use std::io;
-use std::io::Write;
 
 use rand::prelude::*;
-use termion::color;
 
+use crate::common::canvas::Canvas;
 use crate::common::grid::Grid;
 use crate::common::point::Point;
+use crate::common::random::GameRng;
 use crate::tetris::shape::Shape;
 
 const STATE_INIT: u8 = 0;
 const STATE_NORMAL: u8 = 1;
 const STATE_NEW_PIECE: u8 = 2;
 const START_Y: i8 = 2;
+pub const LINE_SCORE: u32 = 1000;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Piece {
     shape: Shape,
     position: Point,
@@ -49,30 +50,36 @@ impl Piece {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Tetris {
     state: u8,
     grid: Grid,
     current_piece: Piece,
     next_shape: Shape,
     score: u32,
+    /// Four lines cleared at once, so far.
+    #[serde(default)]
+    tetrises: u32,
+    rng: GameRng,
 }
 
 impl Tetris {
-    fn random_shape() -> Shape {
+    fn random_shape(rng: &mut GameRng) -> Shape {
         let shapes: Vec<Shape> = Shape::shapes();
-        let mut rng = rand::thread_rng();
-        shapes[rng.gen_range(0, &shapes.len())].clone()
+        shapes[rng.gen_range(0, shapes.len())].clone()
     }
 
-    pub fn new(width: u8, height: u8) -> Tetris {
-        let current_piece = Piece { shape: Tetris::random_shape(), position: Point::new(width as i8 / 2, START_Y) };
+    pub fn new(width: u8, height: u8, mut rng: GameRng) -> Tetris {
+        let current_piece = Piece { shape: Tetris::random_shape(&mut rng), position: Point::new(width as i8 / 2, START_Y) };
+        let next_shape = Tetris::random_shape(&mut rng);
         Tetris {
             state: STATE_INIT,
             grid: Grid::new(width, height),
             current_piece,
-            next_shape: Tetris::random_shape(),
+            next_shape,
             score: 0,
+            tetrises: 0,
+            rng,
         }
     }
 
@@ -85,6 +92,8 @@ impl Tetris {
                 grid: self.current_piece.print(self.grid.clone()),
                 next_shape: self.next_shape.clone(),
                 score: self.score,
+                tetrises: self.tetrises,
+                rng: self.rng.clone(),
             }))
         } else if self.state == STATE_NORMAL {
             let grid = self.current_piece.clear(self.grid.clone());
@@ -97,7 +106,9 @@ impl Tetris {
                     current_piece: piece.clone(),
                     grid: new_grid,
                     next_shape: self.next_shape.clone(),
-                    score: self.score + 1000 * packed as u32,
+                    score: self.score + LINE_SCORE * packed as u32,
+                    tetrises: self.tetrises + (packed == 4) as u32,
+                    rng: self.rng.clone(),
                 }.next())? {
                     Result::Ok(Some(tetris))
                 } else {
@@ -110,6 +121,8 @@ impl Tetris {
                     grid: piece.print(grid),
                     next_shape: self.next_shape.clone(),
                     score: self.score,
+                    tetrises: self.tetrises,
+                    rng: self.rng.clone(),
                 }))
             }
         } else {
@@ -122,13 +135,16 @@ impl Tetris {
             if self.grid.any_occupied(&points)? {
                 Result::Ok(None)
             } else {
-                let next_shape = Tetris::random_shape();
+                let mut rng = self.rng.clone();
+                let next_shape = Tetris::random_shape(&mut rng);
                 Result::Ok(Some(Tetris {
                     state: STATE_NORMAL,
                     current_piece: current_piece.clone(),
                     grid: current_piece.print(self.grid.clone()),
                     next_shape,
                     score: self.score,
+                    tetrises: self.tetrises,
+                    rng,
                 }))
             }
         }
@@ -164,6 +180,8 @@ impl Tetris {
                     grid: piece.print(grid),
                     next_shape: self.next_shape.clone(),
                     score: self.score,
+                    tetrises: self.tetrises,
+                    rng: self.rng.clone(),
                 })
             }
         } else {
@@ -184,27 +202,54 @@ impl Tetris {
                     current_piece: piece.clone(),
                     grid: new_grid,
                     next_shape: self.next_shape.clone(),
-                    score: self.score + 1000 * packed as u32,
+                    score: self.score + LINE_SCORE * packed as u32,
+                    tetrises: self.tetrises + (packed == 4) as u32,
+                    rng: self.rng.clone(),
                 });
             }
             piece = piece_down;
         }
     }
 
-    pub fn print<W: Write>(&self, term: &mut W) -> io::Result<()> {
-        self.grid.print(term, true)
+    pub fn print<C: Canvas>(&self, canvas: &mut C, x: u16, y: u16) {
+        self.grid.print(canvas, x, y, true)
     }
 
-    pub fn print_next_shape<W: Write>(&self, term: &mut W, x: u8, y: u8) -> io::Result<()> {
-        let points = self.next_shape.to_points(0, 0);
-        write!(term, "{}", color::Bg(self.next_shape.color))?;
-        for point in points {
-            write!(term, "{}  ", termion::cursor::Goto((x as i8 + point.x * 2) as u16, (y as i8 + point.y) as u16))?;
+    pub fn print_next_shape<C: Canvas>(&self, canvas: &mut C, x: u16, y: u16) {
+        for point in self.next_shape.to_points(0, 0) {
+            canvas.block((x as i16 + point.x as i16 * 2) as u16, (y as i16 + point.y as i16) as u16, 2,
+                         self.next_shape.color);
         }
-        Result::Ok(())
     }
 
     pub fn score(&self) -> u32 {
         self.score
     }
+
+    pub fn tetrises(&self) -> u32 {
+        self.tetrises
+    }
+
+    /// Lines cleared so far.
+    pub fn lines(&self) -> u32 {
+        self.score / LINE_SCORE
+    }
+
+    /// With the falling shape in it.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Whether a shape is falling, that can be moved; false right after one landed, until the next one comes.
+    pub fn falling(&self) -> bool {
+        self.state == STATE_NORMAL
+    }
+
+    pub fn width(&self) -> u8 {
+        self.grid.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.grid.height
+    }
 }
\ No newline at end of file
@@ -1,125 +1,290 @@
-use std::{io, thread};
-use std::io::{Error, Read, Write};
+use std::cmp::max;
+use std::io;
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::time::Duration;
 
 use termion::event::Key;
-use termion::event::Key::Char;
-use termion::input::TermRead;
 
-use crate::common::persistence::HighScores;
-use crate::Main;
-use crate::tetris::tetris::Tetris;
+use crate::common::canvas::Canvas;
+use crate::common::consolecolor::Color;
+use crate::common::difficulty::Difficulty;
+use crate::common::gameloop::{Flow, Game, GameLoop};
+use crate::common::gamemain::GameMain;
+use crate::common::help::Help;
+use crate::common::keys::{Action, KeyBindings};
+use crate::common::options::GameOptions;
+use crate::common::persistence::{Order, Scoring, Unit};
+use crate::common::random::GameRng;
+use crate::common::result::{Counter, GameResult, Outcome};
+use crate::common::screen::Screen;
+use crate::tetris::bot;
+use crate::tetris::tetris::{LINE_SCORE, Tetris};
+
+const NAME: &str = "Tetris";
+const DIR: &str = "tetris";
+const WIDTH: u8 = 10;
+const HEIGHT: u8 = 20;
+/// How long a shape takes to fall a row, at Easy, Normal and Hard.
+const TICKS: [Duration; 3] = [Duration::from_millis(600), Duration::from_millis(400), Duration::from_millis(250)];
+const SPRINT_NAME: &str = "Tetris Sprint";
+const SPRINT_DIR: &str = "tetris-sprint";
+/// Lines to clear in a sprint, as fast as possible.
+const SPRINT_LINES: u32 = 40;
+const SPRINT_SCORING: Scoring = Scoring { order: Order::LowerIsBetter, unit: Unit::Milliseconds };
 
 pub struct TetrisMain<W: Write, R: Read> {
+    options: GameOptions,
+    /// A sprint is over once `SPRINT_LINES` are cleared, and scored by the time it took.
+    sprint: bool,
     _w_marker: PhantomData<W>,
     _r_marker: PhantomData<R>,
 }
 
 impl<W: Write, R: Read> TetrisMain<W, R> {
-    pub fn new() -> TetrisMain<W, R> {
-        TetrisMain { _w_marker: PhantomData, _r_marker: PhantomData }
+    pub fn new(options: GameOptions) -> TetrisMain<W, R> {
+        TetrisMain { options, sprint: false, _w_marker: PhantomData, _r_marker: PhantomData }
+    }
+
+    pub fn sprint(options: GameOptions) -> TetrisMain<W, R> {
+        TetrisMain { sprint: true, ..TetrisMain::new(options) }
     }
 }
 
-impl<W: Write, R: Read> Main<W, R> for TetrisMain<W, R> {
+impl<W: Write, R: Read> GameMain<W, R> for TetrisMain<W, R> {
+    type Game = TetrisGame;
+    type Saved = Tetris;
+
     fn name(&self) -> &'static str {
-        "Tetris"
-    }
-
-    fn run(&self, mut stdout: &mut W, stdin: &mut R) -> io::Result<Option<u32>> {
-        write!(stdout,
-               "{}{}q to exit, left and right arrow to move{}down to rotate clockwise, up to rotate counterclockwise.\r\n",
-               termion::clear::All,
-               termion::cursor::Goto(1, 1),
-               termion::cursor::Goto(1, 2))?;
-
-        stdout.flush()?;
-
-        let mut tetris = Tetris::new(10, 20);
-
-        let mut result: io::Result<Option<u32>> = Result::Ok(None);
-
-        print(&mut stdout, &mut tetris)?;
-
-        'outer: loop {
-            for _i in 0..40 {
-                let mut key_pressed = false;
-
-                if let Some(key_or_error) = stdin.keys().next() {
-                    let key = key_or_error?;
-
-                    if let Key::Esc = key {
-                        break 'outer;
-                    } else if let Char(' ') = key {
-                        let new_tetris = tetris.fall()?;
-                        tetris = new_tetris;
-                        key_pressed = true;
-                    } else if let Key::Left = key {
-                        tetris = tetris.left()?;
-                        key_pressed = true;
-                    } else if let Key::Right = key {
-                        tetris = tetris.right()?;
-                        key_pressed = true;
-                    } else if let Key::Up = key {
-                        tetris = tetris.rotate_left()?;
-                        key_pressed = true;
-                    } else if let Key::Down = key {
-                        tetris = tetris.rotate_right()?;
-                        key_pressed = true;
-                    }
-                }
-
-                if key_pressed {
-                    print(&mut stdout, &mut tetris)?;
-                }
-
-                thread::sleep(Duration::from_millis(10));
-            }
-
-            if let Ok(Some(new_tetris)) = tetris.next() {
-                tetris = new_tetris;
-
-                print(&mut stdout, &mut tetris)?;
-            } else {
-                result = Ok(Some(tetris.score()));
-                break 'outer;
-            }
+        if self.sprint { SPRINT_NAME } else { NAME }
+    }
+
+    fn dir(&self) -> &'static str {
+        if self.sprint { SPRINT_DIR } else { DIR }
+    }
+
+    fn options(&self) -> &GameOptions {
+        &self.options
+    }
+
+    fn scoring(&self) -> Scoring {
+        if self.sprint { SPRINT_SCORING } else { Scoring::POINTS }
+    }
+
+    /// A sprint is played with the keys of Tetris.
+    fn bindings(&self) -> io::Result<KeyBindings> {
+        KeyBindings::load(NAME, default_bindings())
+    }
+
+    fn explain(&self, bindings: &KeyBindings) -> Help {
+        if self.sprint {
+            Help::new(&format!("Clear {} rows as fast as you can, by filling them with the falling shapes.", SPRINT_LINES),
+                      "Ranked by the time it took. A sprint that was saved and continued is not ranked.", bindings)
+        } else {
+            Help::new("Fill rows with the falling shapes to clear them, before the shapes pile up to the top.",
+                      &format!("{} points for every row cleared.", LINE_SCORE), bindings)
         }
+    }
 
-        while stdin.keys().next().is_some() {}
+    fn size(&self) -> (u8, u8) {
+        self.options.size((WIDTH, HEIGHT), (4, 4))
+    }
 
-        result
+    fn tick(&self) -> Duration {
+        let difficulty = self.options.difficulty;
+        difficulty.preset(TICKS).div_f32(difficulty.speed())
     }
 
-    fn high_scores(&self) -> Result<HighScores, Error> {
-        HighScores::read(".tetris")
+    fn new_game(&self, width: u8, height: u8, bindings: &KeyBindings, rng: GameRng) -> TetrisGame {
+        TetrisGame { ranked: true, ..self.continue_game(Tetris::new(width, height, rng), bindings) }
+    }
+
+    fn continue_game(&self, tetris: Tetris, bindings: &KeyBindings) -> TetrisGame {
+        let help = format!("{} to pause and see the controls.", bindings.keys(Action::Pause));
+        let goal = if self.sprint { Some(SPRINT_LINES) } else { None };
+        TetrisGame { tetris, help, goal, ranked: false }
+    }
+
+    fn saved(&self, game: TetrisGame) -> Tetris {
+        game.tetris
+    }
+
+    fn game_over(&self, game: &TetrisGame, duration: Duration) -> GameResult {
+        let outcome = if game.reached_goal() { Outcome::Cleared } else { Outcome::Lost };
+        // a sprint is ranked by the time it took to clear the lines, and only if they were
+        let score = match outcome {
+            _ if !self.sprint => Some(game.tetris.score()),
+            Outcome::Cleared if game.ranked => Some(duration.as_millis() as u32),
+            _ => None
+        };
+        let mode = format!("{}x{}", game.tetris.width(), game.tetris.height());
+        let stats = vec!(("lines", game.tetris.lines()));
+        let counters = vec!(Counter::Total("lines cleared", game.tetris.lines()),
+                            Counter::Total("tetrises", game.tetris.tetrises()));
+        GameResult::new(outcome, score, duration, mode, stats).counting(counters)
+    }
+
+    fn demo(&self, stdout: &mut W, stdin: &mut R, length: Duration) -> io::Result<bool> {
+        // a sprint would look just like Tetris
+        if self.sprint {
+            return Ok(true);
+        }
+
+        let (width, height) = self.size();
+        let bindings = KeyBindings::new(default_bindings());
+        let help = "Demo: press any key to return to the menu.".to_string();
+
+        let game_loop = GameLoop::new(Difficulty::Normal.preset(TICKS), self.explain(&bindings), bindings).demoing(length);
+
+        let (flow, _) = game_loop.play(stdout, stdin, None, None, |rng|
+            TetrisGame { tetris: Tetris::new(width, height, rng), help: help.clone(), goal: None, ranked: false })?;
+
+        Ok(flow != Flow::Quit)
     }
 }
 
-fn print<W: Write>(mut stdout: &mut W, tetris: &mut Tetris) -> io::Result<()> {
-    write!(stdout,
-           "{}Score: {}",
-           termion::cursor::Goto(1, 3),
-           tetris.score())?;
-    clear_rec(stdout, 25, 5, 10, 5)?;
-    tetris.print_next_shape(stdout, 30, 5)?;
-    goto(&mut stdout, 1, 4)?;
-    tetris.print(&mut stdout)
+fn default_bindings() -> Vec<(Action, Vec<Key>)> {
+    vec!(
+        (Action::MoveLeft, vec!(Key::Left)),
+        (Action::MoveRight, vec!(Key::Right)),
+        (Action::RotateCW, vec!(Key::Down)),
+        (Action::RotateCCW, vec!(Key::Up)),
+        (Action::HardDrop, vec!(Key::Char(' ')))
+    )
 }
 
-fn goto<W: Write>(stdout: &mut W, x: u16, y: u16) -> io::Result<()> {
-    write!(stdout,
-           "{}",
-           termion::cursor::Goto(x, y))
+pub struct TetrisGame {
+    tetris: Tetris,
+    help: String,
+    /// Lines to clear to end the game.
+    goal: Option<u32>,
+    /// The time of a sprint played before it was saved is lost, so a continued sprint can't be ranked.
+    ranked: bool,
 }
 
-pub fn clear_rec<W: Write>(stdout: &mut W, x: u8, y: u8, width: u8, height: u8) -> io::Result<()> {
-    let row = " ".repeat(width as usize);
-    write!(stdout, "{}", termion::style::Reset)?;
-    for iy in y..(y + height) {
-        goto(stdout, x as u16, iy as u16)?;
-        write!(stdout, "{}", row)?;
+impl TetrisGame {
+    fn reached_goal(&self) -> bool {
+        self.goal.map(|goal| self.tetris.lines() >= goal).unwrap_or(false)
+    }
+
+    /// Once the goal is reached the game is over; without a goal it goes on until the grid is full.
+    fn flow(&self) -> Flow {
+        if self.reached_goal() { Flow::GameOver } else { Flow::Continue }
     }
-    Result::Ok(())
+}
+
+impl Game for TetrisGame {
+    fn action(&mut self, action: Action) -> io::Result<Flow> {
+        match action {
+            Action::HardDrop => self.tetris = self.tetris.fall()?,
+            Action::MoveLeft => self.tetris = self.tetris.left()?,
+            Action::MoveRight => self.tetris = self.tetris.right()?,
+            Action::RotateCCW => self.tetris = self.tetris.rotate_left()?,
+            Action::RotateCW => self.tetris = self.tetris.rotate_right()?,
+            _ => {}
+        }
+        Ok(self.flow())
+    }
+
+    fn tick(&mut self) -> io::Result<Flow> {
+        if let Ok(Some(tetris)) = self.tetris.next() {
+            self.tetris = tetris;
+            Ok(self.flow())
+        } else {
+            Ok(Flow::GameOver)
+        }
+    }
+
+    fn render(&self, screen: &mut Screen) {
+        print(screen, &self.tetris, &self.help, self.goal)
+    }
+
+    fn bot(&self) -> Vec<Action> {
+        bot::actions(&self.tetris)
+    }
+
+    fn size(&self) -> (u16, u16) {
+        // the next shape is drawn right of the grid
+        (max(self.help.len() as u16, self.tetris.width() as u16 * 2 + 18), self.tetris.height() as u16 + 5)
+    }
+}
+
+fn print(screen: &mut Screen, tetris: &Tetris, help: &str, goal: Option<u32>) {
+    let status = match goal {
+        Some(goal) => format!("Lines: {}/{}", tetris.lines(), goal),
+        None => format!("Score: {}", tetris.score())
+    };
+    screen.text(1, 1, help, Color::DefaultColor, Color::DefaultColor);
+    screen.text(1, 3, &status, Color::DefaultColor, Color::DefaultColor);
+    tetris.print_next_shape(screen, tetris.width() as u16 * 2 + 10, 5);
+    tetris.print(screen, 1, 4);
+}
+
+#[cfg(test)]
+use crate::common::headless::{assert_snapshot, DOWN, Headless, LEFT, play, RIGHT, Script, UP, VirtualTerminal};
+#[cfg(test)]
+use crate::common::replay::{Replay, Speed};
+#[cfg(test)]
+use crate::Main;
+
+#[test]
+fn tetris_should_be_played_until_the_grid_is_full() {
+    let options = GameOptions { width: Some(6), height: Some(8), seed: Some(1), ..GameOptions::default() };
+    let script = Script::new()
+        .keys(&[LEFT, LEFT, " "]).wait(1000)
+        .keys(&[RIGHT, RIGHT, " "]).wait(1000)
+        .keys(&[DOWN, " "]);
+
+    let (score, terminal) = play(&TetrisMain::new(options), script, 40, 14);
+
+    assert_eq!(Some(0), score.unwrap().score);
+    assert_snapshot("tetris_game_over", &terminal.snapshot());
+}
+
+#[test]
+fn the_last_tetris_game_should_be_replayed_exactly() {
+    let headless = Headless::new(40, 14);
+    let main = TetrisMain::new(GameOptions { width: Some(6), height: Some(8), seed: None, ..GameOptions::default() });
+    let script = Script::new()
+        .keys(&[LEFT, " "]).wait(1200)
+        .keys(&[RIGHT, RIGHT, UP, " "]).wait(800)
+        .keys(&[DOWN, LEFT, LEFT, LEFT]);
+
+    let (score, played) = headless.run(script, |terminal, script| main.run(terminal, script));
+    let replay = Replay::read(&headless.home().join("data").join(DIR).join("replay.json")).unwrap();
+    let (replayed_score, replayed) = headless.run(Script::new(), |terminal, script|
+        TetrisMain::new(replay.options()).replay(terminal, script, replay, Speed::Double));
+
+    assert_eq!(score.unwrap(), replayed_score.unwrap());
+    assert_eq!(played.snapshot(), replayed.snapshot());
+}
+
+#[test]
+fn a_sprint_should_not_be_ranked_when_the_grid_is_full_before_the_lines_are_cleared() {
+    let options = GameOptions { width: Some(6), height: Some(8), seed: Some(1), ..GameOptions::default() };
+    let script = Script::new()
+        .keys(&[LEFT, LEFT, " "]).wait(1000)
+        .keys(&[RIGHT, RIGHT, " "]).wait(1000)
+        .keys(&[DOWN, " "]);
+
+    let (score, terminal) = play(&TetrisMain::sprint(options), script, 40, 14);
+
+    let result = score.unwrap();
+    assert_eq!(Outcome::Lost, result.outcome);
+    assert_eq!(None, result.score);
+    assert!(terminal.snapshot().contains("Lines: 0/40"));
+}
+
+#[test]
+fn the_help_should_show_the_keys_of_the_config_file() {
+    let headless = Headless::new(40, 14);
+    std::fs::create_dir_all(headless.home().join("config")).unwrap();
+    std::fs::write(headless.home().join("config").join("keys.json"), r#"{ "tetris": { "MoveLeft": ["a"] } }"#).unwrap();
+
+    let (help, _) = headless.run(Script::new(), |_, _|
+        Main::<VirtualTerminal, Script>::help(&TetrisMain::new(GameOptions::default())));
+
+    let help = help.unwrap();
+    assert!(help.summary(200)[2].starts_with("Controls: a move left, Right move right,"));
+    assert_eq!("1000 points for every row cleared.", help.scoring);
 }
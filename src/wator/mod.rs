@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 mod wator;
 pub mod watormain;
\ No newline at end of file
@@ -1,23 +1,29 @@
-use std::io;
-use std::io::Write;
-
 use rand::Rng;
-use termion::color;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::common::canvas::Canvas;
+use crate::common::consolecolor::Color;
+use crate::common::glyphs;
 use crate::common::point::Direction;
+use crate::common::random::GameRng;
 
 trait Specie {
     fn mv(&self, north: Option<Box<dyn Specie>>,
           south: Option<Box<dyn Specie>>, east: Option<Box<dyn Specie>>,
-          west: Option<Box<dyn Specie>>) -> MvResult;
+          west: Option<Box<dyn Specie>>, rng: &mut GameRng) -> MvResult;
 
     fn c(&self) -> char;
 
+    /// Tells fish from sharks when half blocks or braille draw several cells in one character.
+    fn color(&self) -> Color;
+
     fn box_clone(&self) -> Box<dyn Specie>;
 
     fn child(&self) -> Box<dyn Specie>;
 
     fn can_be_eaten(&self) -> bool;
+
+    fn state(&self) -> SpecieState;
 }
 
 impl Clone for Box<dyn Specie>
@@ -27,6 +33,28 @@ impl Clone for Box<dyn Specie>
     }
 }
 
+/// What is saved of a specie, since trait objects can't be deserialized directly.
+#[derive(Serialize, Deserialize)]
+enum SpecieState {
+    Fish { life: u16 },
+    Shark { life: u16, energy: u16 },
+}
+
+impl Serialize for Box<dyn Specie> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.state().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Box<dyn Specie> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Box<dyn Specie>, D::Error> {
+        Ok(match SpecieState::deserialize(deserializer)? {
+            SpecieState::Fish { life } => Box::new(Fish { life }),
+            SpecieState::Shark { life, energy } => Box::new(Shark { life, energy })
+        })
+    }
+}
+
 struct MvResult {
     specie: Option<Box<dyn Specie>>,
     direction: Option<Direction>,
@@ -48,12 +76,11 @@ const FISH_REPRODUCTION_TIME: u16 = 50;
 const SHARK_REPRODUCTION_TIME: u16 = 100;
 const SHARK_INITIAL_ENERGY: u16 = 100;
 const ENERGY_GAIN_ON_EAT: u16 = 10;
-const SHARKS: u16 = 10;
 const FISHES: u16 = 100;
 
 impl Specie for Fish {
     fn mv(&self, north: Option<Box<dyn Specie>>, south: Option<Box<dyn Specie>>,
-          east: Option<Box<dyn Specie>>, west: Option<Box<dyn Specie>>) -> MvResult {
+          east: Option<Box<dyn Specie>>, west: Option<Box<dyn Specie>>, rng: &mut GameRng) -> MvResult {
         let mut life = self.life + 1;
 
         let child = life > FISH_REPRODUCTION_TIME;
@@ -64,26 +91,25 @@ impl Specie for Fish {
 
         let mut possible_movements: Vec<Direction> = Vec::new();
 
-        if let None = north {
+        if north.is_none() {
             possible_movements.push(Direction::North)
         }
 
-        if let None = south {
+        if south.is_none() {
             possible_movements.push(Direction::South)
         }
 
-        if let None = west {
+        if west.is_none() {
             possible_movements.push(Direction::West)
         }
 
-        if let None = east {
+        if east.is_none() {
             possible_movements.push(Direction::East)
         }
 
         let movement = if possible_movements.is_empty() {
             None
         } else {
-            let mut rng = rand::thread_rng();
             Some(possible_movements[rng.gen_range(0, possible_movements.len())].clone())
         };
 
@@ -95,6 +121,10 @@ impl Specie for Fish {
         '.'
     }
 
+    fn color(&self) -> Color {
+        Color::Green
+    }
+
     fn box_clone(&self) -> Box<dyn Specie> {
         Box::new((*self).clone())
     }
@@ -106,6 +136,10 @@ impl Specie for Fish {
     fn can_be_eaten(&self) -> bool {
         true
     }
+
+    fn state(&self) -> SpecieState {
+        SpecieState::Fish { life: self.life }
+    }
 }
 
 #[derive(Clone)]
@@ -122,7 +156,7 @@ impl Shark {
 
 impl Specie for Shark {
     fn mv(&self, north: Option<Box<dyn Specie>>, south: Option<Box<dyn Specie>>,
-          east: Option<Box<dyn Specie>>, west: Option<Box<dyn Specie>>) -> MvResult {
+          east: Option<Box<dyn Specie>>, west: Option<Box<dyn Specie>>, rng: &mut GameRng) -> MvResult {
         let mut life = self.life + 1;
 
         let child = life > SHARK_REPRODUCTION_TIME;
@@ -140,34 +174,30 @@ impl Specie for Shark {
         let mut possible_movements: Vec<Direction> = Vec::new();
         let mut possible_eats: Vec<Direction> = Vec::new();
 
-        if let None = north {
-            possible_movements.push(Direction::North)
-        } else if let Some(s) = north {
-            if s.can_be_eaten() {
+        match north {
+            None => possible_movements.push(Direction::North),
+            Some(s) => if s.can_be_eaten() {
                 possible_eats.push(Direction::North)
             }
         }
 
-        if let None = south {
-            possible_movements.push(Direction::South)
-        } else if let Some(s) = south {
-            if s.can_be_eaten() {
+        match south {
+            None => possible_movements.push(Direction::South),
+            Some(s) => if s.can_be_eaten() {
                 possible_eats.push(Direction::South)
             }
         }
 
-        if let None = west {
-            possible_movements.push(Direction::West)
-        } else if let Some(s) = west {
-            if s.can_be_eaten() {
+        match west {
+            None => possible_movements.push(Direction::West),
+            Some(s) => if s.can_be_eaten() {
                 possible_eats.push(Direction::West)
             }
         }
 
-        if let None = east {
-            possible_movements.push(Direction::East)
-        } else if let Some(s) = east {
-            if s.can_be_eaten() {
+        match east {
+            None => possible_movements.push(Direction::East),
+            Some(s) => if s.can_be_eaten() {
                 possible_eats.push(Direction::East)
             }
         }
@@ -176,11 +206,9 @@ impl Specie for Shark {
 
         if possible_eats.is_empty() {
             if !possible_movements.is_empty() {
-                let mut rng = rand::thread_rng();
                 movement = Some(possible_movements[rng.gen_range(0, possible_movements.len())].clone())
             }
         } else {
-            let mut rng = rand::thread_rng();
             energy += ENERGY_GAIN_ON_EAT;
             movement = Some(possible_eats[rng.gen_range(0, possible_eats.len())].clone())
         }
@@ -194,6 +222,10 @@ impl Specie for Shark {
         '#'
     }
 
+    fn color(&self) -> Color {
+        Color::Red
+    }
+
     fn box_clone(&self) -> Box<dyn Specie> {
         Box::new((*self).clone())
     }
@@ -205,17 +237,23 @@ impl Specie for Shark {
     fn can_be_eaten(&self) -> bool {
         false
     }
+
+    fn state(&self) -> SpecieState {
+        SpecieState::Shark { life: self.life, energy: self.energy }
+    }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Wator {
     width: u8,
     height: u8,
     population: Vec<Vec<Option<Box<dyn Specie>>>>,
     time: u32,
+    rng: GameRng,
 }
 
 impl Wator {
-    pub fn new(width: u8, height: u8) -> Wator {
+    pub fn new(width: u8, height: u8, sharks: u16, mut rng: GameRng) -> Wator {
         let mut population: Vec<Vec<Option<Box<dyn Specie>>>> = vec![];
 
         for _y in 0..height {
@@ -223,8 +261,6 @@ impl Wator {
             population.push(row);
         }
 
-        let mut rng = rand::thread_rng();
-
         let mut fishes = FISHES;
 
         while fishes > 0 {
@@ -237,7 +273,7 @@ impl Wator {
             }
         }
 
-        let mut sharks = SHARKS;
+        let mut sharks = sharks;
 
         while sharks > 0 {
             let x = rng.gen_range(0, width as usize);
@@ -249,34 +285,25 @@ impl Wator {
             }
         }
 
-        Wator { width, height, population, time: 0 }
+        Wator { width, height, population, time: 0, rng }
     }
 
     pub fn next(&self) -> Wator {
-        let mut population: Vec<Vec<Option<Box<dyn Specie>>>> = vec![];
+        let mut population = self.population.clone();
 
-        for _y in 0..self.height {
-            let row = Wator::create_empty_row(self.width);
-            population.push(row);
-        }
-
-        for y in 0..self.height as usize {
-            for x in 0..self.width as usize {
-                population[y][x] = self.population[y][x].clone();
-            }
-        }
+        let mut rng = self.rng.clone();
 
         for y in 0..self.height as usize {
             for x in 0..self.width as usize {
-                let north = self.safe_get(x as i8, y as i8 - 1, &population);
-                let south = self.safe_get(x as i8, y as i8 + 1, &population);
-                let east = self.safe_get(x as i8 + 1, y as i8, &population);
-                let west = self.safe_get(x as i8 - 1, y as i8, &population);
+                let north = self.safe_get(x as i16, y as i16 - 1, &population);
+                let south = self.safe_get(x as i16, y as i16 + 1, &population);
+                let east = self.safe_get(x as i16 + 1, y as i16, &population);
+                let west = self.safe_get(x as i16 - 1, y as i16, &population);
 
                 if let Some(specie) = &population[y][x].clone() {
                     population[y][x] = None;
 
-                    let movement_result = specie.mv(north, south, east, west);
+                    let movement_result = specie.mv(north, south, east, west, &mut rng);
 
                     if let Some(specie) = movement_result.specie {
                         if let Some(mv) = movement_result.direction {
@@ -285,13 +312,13 @@ impl Wator {
                             }
 
                             match mv {
-                                Direction::North => self.safe_put(x as i8, y as i8 - 1, &mut population,
+                                Direction::North => self.safe_put(x as i16, y as i16 - 1, &mut population,
                                                                   specie),
-                                Direction::South => self.safe_put(x as i8, y as i8 + 1, &mut population,
+                                Direction::South => self.safe_put(x as i16, y as i16 + 1, &mut population,
                                                                   specie),
-                                Direction::West => self.safe_put(x as i8 - 1, y as i8, &mut population,
+                                Direction::West => self.safe_put(x as i16 - 1, y as i16, &mut population,
                                                                  specie),
-                                Direction::East => self.safe_put(x as i8 + 1, y as i8, &mut population,
+                                Direction::East => self.safe_put(x as i16 + 1, y as i16, &mut population,
                                                                  specie)
                             }
                         } else {
@@ -302,7 +329,29 @@ impl Wator {
             }
         }
 
-        Wator { width: self.width, height: self.height, population, time: self.time + 1 }
+        Wator { width: self.width, height: self.height, population, time: self.time + 1, rng }
+    }
+
+    /// Unless something is there already.
+    pub fn drop_fish(&self, x: u8, y: u8) -> Wator {
+        self.drop(x, y, Box::new(Fish::new()))
+    }
+
+    /// Unless something is there already.
+    pub fn drop_shark(&self, x: u8, y: u8) -> Wator {
+        self.drop(x, y, Box::new(Shark::new()))
+    }
+
+    fn drop(&self, x: u8, y: u8, specie: Box<dyn Specie>) -> Wator {
+        let mut population = self.population.clone();
+
+        if let Some(cell) = population.get_mut(y as usize).and_then(|row| row.get_mut(x as usize)) {
+            if cell.is_none() {
+                *cell = Some(specie);
+            }
+        }
+
+        Wator { width: self.width, height: self.height, population, time: self.time, rng: self.rng.clone() }
     }
 
     pub fn count(&self) -> (u16, u16) {
@@ -323,31 +372,59 @@ impl Wator {
         (fishes, sharks)
     }
 
-    pub fn print<W: Write>(&self, term: &mut W, border: bool) -> io::Result<()> {
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    /// The size of the sea on the canvas, when `density` cells are drawn across and down each character.
+    pub fn area(&self, density: (u16, u16)) -> (u16, u16) {
+        ((self.width as u16).div_ceil(density.0), (self.height as u16).div_ceil(density.1))
+    }
+
+    /// Draws `density` cells across and down each character: one as the character of its species, two one above
+    /// the other as half blocks, or four as braille dots.
+    pub fn print<C: Canvas>(&self, canvas: &mut C, x: u16, y: u16, border: bool, density: (u16, u16)) {
         let (fishes, sharks) = self.count();
-        write!(term, "Time: {}  Fishes: {}  Sharks: {}\n\r", self.time, fishes, sharks)?;
+        canvas.text(x, y, &format!("Time: {}  Fishes: {}  Sharks: {}", self.time, fishes, sharks),
+                    Color::DefaultColor, Color::DefaultColor);
 
-        if border { self.print_border_row(term)?; }
+        let offset = if border { 1 } else { 0 };
+        let (width, height) = self.area(density);
 
-        for row in &self.population {
-            if border { write!(term, "{} {}", color::Bg(color::White), termion::style::Reset)?; }
+        if border {
+            canvas.border(x, y + 1, width + 2, height + 2);
+        }
 
-            for s in row {
-                if let Some(_specie) = s {
-                    write!(term, "{}", s.as_ref().unwrap().c())?;
-                } else {
-                    write!(term, " ")?;
+        for cy in 0..height {
+            for cx in 0..width {
+                let cell = |dx: u16, dy: u16| self.population.get((cy * density.1 + dy) as usize)
+                    .and_then(|row| row.get((cx * density.0 + dx) as usize))
+                    .and_then(|specie| specie.as_ref());
+
+                let glyph = match density {
+                    (1, 1) => cell(0, 0).map(|specie| (specie.c(), Color::DefaultColor, Color::DefaultColor)),
+                    (1, _) => half_block(cell(0, 0).map(|specie| specie.color()), cell(0, 1).map(|specie| specie.color())),
+                    _ => braille(&[(0, 0), (1, 0), (0, 1), (1, 1)].iter()
+                        .filter_map(|&(dx, dy)| cell(dx, dy).map(|specie| (dx, dy, specie.as_ref())))
+                        .collect::<Vec<_>>())
+                };
+
+                if let Some((c, fg, bg)) = glyph {
+                    canvas.put(x + offset + cx, y + 1 + offset + cy, c, fg, bg);
                 }
             }
-            if border { write!(term, "{} {}\n\r", color::Bg(color::White), termion::style::Reset)?; } else { write!(term, "{}\n\r", termion::style::Reset)?; }
         }
-
-        if border { self.print_border_row(term)?; }
-
-        term.flush()
     }
 
-    fn safe_get(&self, x: i8, y: i8, population: &Vec<Vec<Option<Box<dyn Specie>>>>) -> Option<Box<dyn Specie>> {
+    fn safe_get(&self, x: i16, y: i16, population: &[Vec<Option<Box<dyn Specie>>>]) -> Option<Box<dyn Specie>> {
         let (ix, iy) = self.safe_position(x, y);
 
         if let Some(s) = population[iy as usize][ix as usize].as_ref() {
@@ -357,38 +434,30 @@ impl Wator {
         }
     }
 
-    fn safe_put(&self, x: i8, y: i8, population: &mut Vec<Vec<Option<Box<dyn Specie>>>>, specie: Box<dyn Specie>) {
+    fn safe_put(&self, x: i16, y: i16, population: &mut [Vec<Option<Box<dyn Specie>>>], specie: Box<dyn Specie>) {
         let (ix, iy) = self.safe_position(x, y);
 
         population[iy as usize][ix as usize] = Some(specie.clone());
     }
 
-    fn safe_position(&self, x: i8, y: i8) -> (i8, i8) {
+    fn safe_position(&self, x: i16, y: i16) -> (i16, i16) {
         let ix = if x < 0 {
-            x + self.width as i8
-        } else if x >= self.width as i8 {
-            x - self.width as i8
+            x + self.width as i16
+        } else if x >= self.width as i16 {
+            x - self.width as i16
         } else {
             x
         };
         let iy = if y < 0 {
-            y + self.height as i8
-        } else if y >= self.height as i8 {
-            y - self.height as i8
+            y + self.height as i16
+        } else if y >= self.height as i16 {
+            y - self.height as i16
         } else {
             y
         };
         (ix, iy)
     }
 
-    fn print_border_row<W: Write>(&self, term: &mut W) -> io::Result<()> {
-        write!(term, "{} ", color::Bg(color::White))?;
-        for _ in 0..self.width {
-            write!(term, " ")?;
-        }
-        write!(term, " {}\n\r", termion::style::Reset)
-    }
-
     fn create_empty_row(width: u8) -> Vec<Option<Box<dyn Specie>>> {
         let mut row: Vec<Option<Box<dyn Specie>>> = vec![];
         for _x in 0..width {
@@ -396,4 +465,36 @@ impl Wator {
         }
         row
     }
-}
\ No newline at end of file
+}
+
+/// The upper cell in the foreground of ▀, and the lower one in its background; ▄ when only the lower one is there.
+fn half_block(upper: Option<Color>, lower: Option<Color>) -> Option<(char, Color, Color)> {
+    match (upper, lower) {
+        (Some(upper), lower) => Some(('▀', upper, lower.unwrap_or(Color::DefaultColor))),
+        (None, Some(lower)) => Some(('▄', lower, Color::DefaultColor)),
+        (None, None) => None
+    }
+}
+
+/// The dots of the species there are, in the color of the sharks if there is one among them.
+fn braille(cells: &[(u16, u16, &dyn Specie)]) -> Option<(char, Color, Color)> {
+    let dots = cells.iter().fold(0, |dots, &(x, y, _)| dots | glyphs::braille_dots(x, y));
+    let color = cells.iter().find(|(_, _, specie)| !specie.can_be_eaten()).or_else(|| cells.first())
+        .map(|(_, _, specie)| specie.color())?;
+
+    std::char::from_u32(0x2800 + dots as u32).map(|c| (c, color, Color::DefaultColor))
+}
+
+#[cfg(test)]
+use crate::common::random::new_rng;
+
+#[test]
+fn given_a_saved_wator_then_it_should_be_restored_with_every_specie() {
+    let wator = Wator::new(15, 10, 10, new_rng(1)).next().next();
+
+    let restored: Wator = serde_json::from_str(&serde_json::to_string(&wator).unwrap()).unwrap();
+
+    assert_eq!(serde_json::to_value(&wator).unwrap(), serde_json::to_value(&restored).unwrap());
+    assert_eq!(2, restored.time());
+    assert_eq!(serde_json::to_value(wator.next()).unwrap(), serde_json::to_value(restored.next()).unwrap());
+}
@@ -1,81 +1,235 @@
-use std::{io, thread};
-use std::io::{Error, Read};
+use std::cmp::max;
+use std::io;
+use std::io::Read;
 use std::io::Write;
 use std::marker::PhantomData;
 use std::time::Duration;
 
-use termion::event::Key;
-use termion::input::TermRead;
-
-use crate::common::persistence::HighScores;
-use crate::Main;
+use crate::common::difficulty::Difficulty;
+use crate::common::gameloop::{Flow, Game, GameLoop};
+use crate::common::gamemain::GameMain;
+use crate::common::glyphs;
+use crate::common::help::Help;
+use crate::common::keys::{Action, Button, KeyBindings};
+use crate::common::options::GameOptions;
+use crate::common::persistence::{Order, Scoring, Unit};
+use crate::common::random::GameRng;
+use crate::common::result::{Counter, GameResult, Outcome};
+use crate::common::screen::Screen;
+use crate::common::theme;
 use crate::wator::wator::Wator;
 
+const NAME: &str = "Wa-tor";
+const DIR: &str = "wator";
+const WIDTH: u8 = 80;
+const HEIGHT: u8 = 40;
+const TICK: Duration = Duration::from_millis(50);
+/// Sharks at first, at Easy, Normal and Hard: the more there are, the sooner the fishes die out.
+const SHARKS: [u16; 3] = [5, 10, 20];
+/// The longer fishes and sharks live together, the better.
+const SCORING: Scoring = Scoring { order: Order::HigherIsBetter, unit: Unit::Ticks };
+/// Room for the time and the counts written above the sea.
+const HEADER_WIDTH: u16 = 40;
+/// Where the top left cell of the sea is drawn.
+const SEA_X: u16 = 2;
+const SEA_Y: u16 = 3;
+
 pub struct WatorMain<W: Write, R: Read> {
+    options: GameOptions,
     _w_marker: PhantomData<W>,
     _r_marker: PhantomData<R>,
 }
 
 impl<W: Write, R: Read> WatorMain<W, R> {
-    pub fn new() -> WatorMain<W, R> {
-        WatorMain { _w_marker: PhantomData, _r_marker: PhantomData }
+    pub fn new(options: GameOptions) -> WatorMain<W, R> {
+        WatorMain { options, _w_marker: PhantomData, _r_marker: PhantomData }
+    }
+
+    /// The default sea shows as many more cells as are drawn in each character.
+    fn sea_size(&self, density: (u16, u16)) -> (u8, u8) {
+        let default = ((WIDTH as u16 * density.0) as u8, (HEIGHT as u16 * density.1) as u8);
+        self.options.size(default, (15, 10))
     }
 }
 
-impl<W: Write, R: Read> Main<W, R> for WatorMain<W, R> {
+impl<W: Write, R: Read> GameMain<W, R> for WatorMain<W, R> {
+    type Game = WatorGame;
+    type Saved = Wator;
+
     fn name(&self) -> &'static str {
-        "Wa-tor"
+        NAME
     }
 
-    fn run(&self, mut stdout: &mut W, stdin: &mut R) -> io::Result<Option<u32>> {
-        write!(stdout,
-               "{}{}{}",
-               termion::clear::All,
-               termion::cursor::Goto(1, 1),
-               termion::cursor::Goto(1, 2))?;
+    fn dir(&self) -> &'static str {
+        DIR
+    }
 
-        stdout.flush()?;
+    fn options(&self) -> &GameOptions {
+        &self.options
+    }
 
-        let mut wator = Wator::new(80, 40);
+    fn scoring(&self) -> Scoring {
+        SCORING
+    }
 
-        let mut time: u32 = 0;
+    fn bindings(&self) -> io::Result<KeyBindings> {
+        KeyBindings::load(NAME, Vec::new())
+    }
 
-        let mut result: io::Result<Option<u32>> = Result::Ok(None);
+    fn explain(&self, bindings: &KeyBindings) -> Help {
+        explain(bindings)
+    }
 
-        loop {
-            time += 1;
+    fn size(&self) -> (u8, u8) {
+        self.sea_size(density())
+    }
 
-            print(&mut stdout, &mut wator)?;
+    fn tick(&self) -> Duration {
+        TICK.div_f32(self.options.difficulty.speed())
+    }
 
-            let b = stdin.keys().next();
-            if let Some(Ok(Key::Esc)) = b {
-                break;
-            }
-            thread::sleep(Duration::from_millis(50));
-            wator = wator.next();
+    fn new_game(&self, width: u8, height: u8, _: &KeyBindings, rng: GameRng) -> WatorGame {
+        let sharks = self.options.difficulty.preset(SHARKS);
+        WatorGame { wator: Wator::new(width, height, sharks, rng), density: density() }
+    }
 
-            let (fishes, sharks) = wator.count();
+    fn continue_game(&self, wator: Wator, _: &KeyBindings) -> WatorGame {
+        WatorGame { wator, density: density() }
+    }
+
+    fn saved(&self, game: WatorGame) -> Wator {
+        game.wator
+    }
+
+    fn game_over(&self, game: &WatorGame, duration: Duration) -> GameResult {
+        let (fishes, sharks) = game.wator.count();
+        let mode = format!("{}x{}", game.wator.width(), game.wator.height());
+        let stats = vec!(("fishes", fishes as u32), ("sharks", sharks as u32));
+        let counters = vec!(Counter::Longest("longest coexistence (ticks)", game.wator.time()));
+        let score = Some(game.wator.time());
+        GameResult::new(Outcome::Lost, score, duration, mode, stats).counting(counters)
+    }
+
+    /// There is nothing to play: the sea just goes on.
+    fn demo(&self, stdout: &mut W, stdin: &mut R, length: Duration) -> io::Result<bool> {
+        let density = density();
+        let (width, height) = self.sea_size(density);
+        let bindings = KeyBindings::new(Vec::new());
+        let sharks = Difficulty::Normal.preset(SHARKS);
+
+        let game_loop = GameLoop::new(TICK, explain(&bindings), bindings).demoing(length);
 
-            if fishes == 0 || sharks == 0 {
-                result = Result::Ok(Some(time));
-                break;
+        let (flow, _) = game_loop.play(stdout, stdin, None, None, |rng|
+            WatorGame { wator: Wator::new(width, height, sharks, rng), density })?;
+
+        Ok(flow != Flow::Quit)
+    }
+}
+
+fn explain(bindings: &KeyBindings) -> Help {
+    Help::new("Watch fishes and sharks on a sea without shores: fishes breed, sharks eat them or starve. \
+               It ends when a species dies out. Click to drop a fish, right click to drop a shark.",
+              "The ticks both species lived together.", bindings)
+}
+
+/// The cells drawn across and down each character, as the glyphs allow; only colors tell the species apart
+/// when there are several.
+fn density() -> (u16, u16) {
+    if theme::current().colorless() { (1, 1) } else { glyphs::current().density() }
+}
+
+pub struct WatorGame {
+    wator: Wator,
+    density: (u16, u16),
+}
+
+impl Game for WatorGame {
+    fn action(&mut self, action: Action) -> io::Result<Flow> {
+        if let Action::Pointer(button, x, y) = action {
+            // the sea is drawn inside a border, under the counts
+            if let (Some(x), Some(y)) = (x.checked_sub(SEA_X), y.checked_sub(SEA_Y)) {
+                let (x, y) = (x * self.density.0, y * self.density.1);
+                let (x, y) = (x.min(u8::MAX as u16) as u8, y.min(u8::MAX as u16) as u8);
+
+                self.wator = match button {
+                    Button::Left => self.wator.drop_fish(x, y),
+                    Button::Right => self.wator.drop_shark(x, y)
+                };
             }
         }
 
-        while stdin.keys().next().is_some() {}
+        Ok(Flow::Continue)
+    }
+
+    fn tick(&mut self) -> io::Result<Flow> {
+        self.wator = self.wator.next();
+
+        let (fishes, sharks) = self.wator.count();
+
+        if fishes == 0 || sharks == 0 {
+            Ok(Flow::GameOver)
+        } else {
+            Ok(Flow::Continue)
+        }
+    }
 
-        result
+    fn render(&self, screen: &mut Screen) {
+        self.wator.print(screen, 1, 1, true, self.density)
     }
 
-    fn high_scores(&self) -> Result<HighScores, Error> {
-        HighScores::read(".wator")
+    fn size(&self) -> (u16, u16) {
+        let (width, height) = self.wator.area(self.density);
+        (max(width + 2, HEADER_WIDTH), height + 3)
     }
 }
 
-fn print<W: Write>(mut stdout: &mut W, wator: &Wator) -> io::Result<()> {
-    write!(stdout,
-           "{}{}",
-           termion::clear::All,
-           termion::cursor::Goto(1, 1))?;
-    wator.print(&mut stdout, true)
-}
\ No newline at end of file
+#[cfg(test)]
+use crate::common::headless::{assert_snapshot, DOWN, ENTER, ESC, play, Script};
+
+#[test]
+fn wator_should_run_until_a_species_dies_out() {
+    let options = GameOptions { width: Some(15), height: Some(10), seed: Some(1), ..GameOptions::default() };
+
+    let (time, terminal) = play(&WatorMain::new(options), Script::new(), 44, 14);
+
+    assert_eq!(Some(20), time.unwrap().score);
+    assert_snapshot("wator_extinction", &terminal.snapshot());
+}
+
+#[test]
+fn wator_should_not_give_a_time_when_quit() {
+    let options = GameOptions { width: Some(15), height: Some(10), seed: Some(1), ..GameOptions::default() };
+    let script = Script::new().wait(500).keys(&[ESC, DOWN, DOWN, DOWN, DOWN, ENTER, DOWN, ENTER]);
+
+    let (time, terminal) = play(&WatorMain::new(options), script, 44, 14);
+
+    assert_eq!(Outcome::Quit, time.unwrap().outcome);
+    assert_snapshot("wator_quit", &terminal.frames()[terminal.frames().len() - 1]);
+}
+
+#[test]
+fn a_right_click_should_drop_a_shark_where_there_is_nothing() {
+    let options = GameOptions { width: Some(15), height: Some(10), seed: Some(1), ..GameOptions::default() };
+    // along the 2nd row of the sea, which is drawn from the 4th column and 3rd row of the terminal; one cell is empty
+    let script = Script::new().keys(&["\x1b[<2;5;4M", "\x1b[<2;6;4M", "\x1b[<2;7;4M", "\x1b[<2;8;4M", "\x1b[<2;9;4M"]);
+
+    let (_, terminal) = play(&WatorMain::new(options), script, 44, 14);
+
+    let header = |frame: &String| frame.lines().next().unwrap().trim().to_string();
+    assert_eq!("Time: 0  Fishes: 100  Sharks: 10", header(&terminal.frames()[0]));
+    assert_eq!("Time: 0  Fishes: 100  Sharks: 11", header(&terminal.frames()[1]));
+}
+
+#[test]
+fn half_blocks_should_draw_two_cells_in_each_character() {
+    glyphs::set(glyphs::Glyphs::HalfBlocks);
+
+    let options = GameOptions { width: Some(15), height: Some(10), seed: Some(1), ..GameOptions::default() };
+
+    let (time, terminal) = play(&WatorMain::new(options), Script::new(), 44, 9);
+
+    glyphs::set(glyphs::Glyphs::Ascii);
+
+    assert_eq!(Some(20), time.unwrap().score);
+    assert_snapshot("wator_half_blocks", &terminal.snapshot());
+}